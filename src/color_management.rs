@@ -0,0 +1,271 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::tiff::TiffDecoder;
+use image::{ImageDecoder, ImageFormat};
+use moxcms::{ColorProfile, DataColorSpace, Layout, ProfileText, TransformOptions};
+
+use crate::opengl::texture::texel_format::TextureImage;
+
+pub fn read_embedded_profile(bytes: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    match format {
+        ImageFormat::Png => {
+            let mut decoder = PngDecoder::new(Cursor::new(bytes)).ok()?;
+            decoder.icc_profile()
+        }
+        ImageFormat::Jpeg => {
+            let mut decoder = JpegDecoder::new(Cursor::new(bytes)).ok()?;
+            decoder.icc_profile()
+        }
+        ImageFormat::Tiff => {
+            let mut decoder = TiffDecoder::new(Cursor::new(bytes)).ok()?;
+            decoder.icc_profile()
+        }
+        _ => None,
+    }
+}
+
+pub fn parse_profile(icc_data: &[u8]) -> Result<ColorProfile, String> {
+    ColorProfile::new_from_slice(icc_data).map_err(|err| format!("Invalid ICC profile: {}", err))
+}
+
+pub fn profile_name(profile: &ColorProfile) -> String {
+    let name = match &profile.description {
+        Some(ProfileText::PlainString(name)) => Some(name.clone()),
+        Some(ProfileText::Localizable(names)) => names
+            .iter()
+            .find(|name| name.language == "en")
+            .or_else(|| names.first())
+            .map(|name| name.value.clone()),
+        Some(ProfileText::Description(description)) => Some(description.ascii_string.clone()),
+        None => None,
+    };
+
+    name.map(|name| name.trim_matches(char::from(0)).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("Unnamed profile"))
+}
+
+pub struct ColorManagement {
+    display_profile: ColorProfile,
+    display_profile_name: String,
+    enabled: bool,
+}
+
+impl ColorManagement {
+    pub const DISPLAY_PROFILE_ENV_VAR: &'static str = "IMDRIP_DISPLAY_PROFILE";
+
+    pub fn new(display_profile: ColorProfile) -> Self {
+        let display_profile_name = profile_name(&display_profile);
+        Self {
+            display_profile,
+            display_profile_name,
+            enabled: true,
+        }
+    }
+
+    // The display profile is either one of the built-in ones ("srgb",
+    // "display-p3", "adobe-rgb") or the path of an ICC profile
+    pub fn display_profile_from_name(name: &str) -> Result<ColorProfile, String> {
+        match name.to_lowercase().as_str() {
+            "srgb" => Ok(ColorProfile::new_srgb()),
+            "display-p3" | "p3" => Ok(ColorProfile::new_display_p3()),
+            "adobe-rgb" => Ok(ColorProfile::new_adobe_rgb()),
+            _ => {
+                let path = Path::new(name);
+                let icc_data = std::fs::read(path).map_err(|err| {
+                    format!(
+                        "Failed to read display profile {}: {}",
+                        path.to_string_lossy(),
+                        err
+                    )
+                })?;
+                parse_profile(&icc_data)
+            }
+        }
+    }
+
+    pub fn srgb() -> Self {
+        Self::new(ColorProfile::new_srgb())
+    }
+
+    // Uses sRGB if the variable isn't set
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var(Self::DISPLAY_PROFILE_ENV_VAR) {
+            Ok(name) => Self::display_profile_from_name(&name).map(Self::new),
+            Err(_) => Ok(Self::srgb()),
+        }
+    }
+
+    pub fn display_profile_name(&self) -> &str {
+        &self.display_profile_name
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle_enabled(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn convert(&self, image: &mut TextureImage, source: &ColorProfile) -> Result<(), String> {
+        if source.color_space != DataColorSpace::Rgb {
+            return Err(format!(
+                "Profile color space {:?} is not supported",
+                source.color_space
+            ));
+        }
+
+        // Grayscale images have to be expanded to hold the converted colors
+        if image.format().channel_count() < 3 {
+            image.expand_to_rgba();
+        }
+
+        let options = TransformOptions::default();
+        let transform_error = |err: moxcms::CmsError| format!("Color conversion failed: {}", err);
+
+        match image {
+            TextureImage::Rgb8(image) => {
+                let transform = source
+                    .create_transform_8bit(Layout::Rgb, &self.display_profile, Layout::Rgb, options)
+                    .map_err(transform_error)?;
+                let source_data = image.as_raw().clone();
+                let destination: &mut [u8] = &mut *image;
+                transform
+                    .transform(&source_data, destination)
+                    .map_err(transform_error)
+            }
+            TextureImage::Rgba8(image) => {
+                let transform = source
+                    .create_transform_8bit(
+                        Layout::Rgba,
+                        &self.display_profile,
+                        Layout::Rgba,
+                        options,
+                    )
+                    .map_err(transform_error)?;
+                let source_data = image.as_raw().clone();
+                let destination: &mut [u8] = &mut *image;
+                transform
+                    .transform(&source_data, destination)
+                    .map_err(transform_error)
+            }
+            TextureImage::Rgb16(image) => {
+                let transform = source
+                    .create_transform_16bit(
+                        Layout::Rgb,
+                        &self.display_profile,
+                        Layout::Rgb,
+                        options,
+                    )
+                    .map_err(transform_error)?;
+                let source_data = image.as_raw().clone();
+                let destination: &mut [u16] = &mut *image;
+                transform
+                    .transform(&source_data, destination)
+                    .map_err(transform_error)
+            }
+            TextureImage::Rgba16(image) => {
+                let transform = source
+                    .create_transform_16bit(
+                        Layout::Rgba,
+                        &self.display_profile,
+                        Layout::Rgba,
+                        options,
+                    )
+                    .map_err(transform_error)?;
+                let source_data = image.as_raw().clone();
+                let destination: &mut [u16] = &mut *image;
+                transform
+                    .transform(&source_data, destination)
+                    .map_err(transform_error)
+            }
+            TextureImage::Rgba32F(image) => {
+                let transform = source
+                    .create_transform_f32(
+                        Layout::Rgba,
+                        &self.display_profile,
+                        Layout::Rgba,
+                        options,
+                    )
+                    .map_err(transform_error)?;
+                let source_data = image.as_raw().clone();
+                let destination: &mut [f32] = &mut *image;
+                transform
+                    .transform(&source_data, destination)
+                    .map_err(transform_error)
+            }
+            _ => unreachable!("Grayscale images were expanded to RGBA"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_pixel(source: ColorProfile, pixel: [u8; 3]) -> [u8; 3] {
+        let mut image =
+            TextureImage::Rgb8(image::RgbImage::from_raw(1, 1, pixel.to_vec()).unwrap());
+        ColorManagement::srgb()
+            .convert(&mut image, &source)
+            .unwrap();
+        match image {
+            TextureImage::Rgb8(image) => image.get_pixel(0, 0).0,
+            _ => panic!("The image format changed"),
+        }
+    }
+
+    fn assert_close(actual: [u8; 3], expected: [u8; 3]) {
+        let close = actual
+            .iter()
+            .zip(expected.iter())
+            .all(|(actual, expected)| actual.abs_diff(*expected) <= 2);
+        assert!(close, "Expected {:?}, got {:?}", expected, actual);
+    }
+
+    // The reference values were computed from the primaries and transfer
+    // functions of the color spaces
+    #[test]
+    fn converts_display_p3_to_srgb() {
+        let converted = convert_pixel(ColorProfile::new_display_p3(), [200, 100, 50]);
+        assert_close(converted, [215, 93, 31]);
+    }
+
+    #[test]
+    fn converts_adobe_rgb_to_srgb() {
+        let converted = convert_pixel(ColorProfile::new_adobe_rgb(), [200, 100, 50]);
+        assert_close(converted, [227, 100, 42]);
+    }
+
+    #[test]
+    fn keeps_neutral_colors() {
+        for source in [ColorProfile::new_display_p3(), ColorProfile::new_srgb()] {
+            assert_close(convert_pixel(source, [128, 128, 128]), [128, 128, 128]);
+        }
+        assert_close(
+            convert_pixel(ColorProfile::new_adobe_rgb(), [255, 255, 255]),
+            [255, 255, 255],
+        );
+    }
+
+    #[test]
+    fn expands_grayscale_images() {
+        let mut image = TextureImage::Luma8(image::GrayImage::from_raw(1, 1, vec![255]).unwrap());
+        ColorManagement::srgb()
+            .convert(&mut image, &ColorProfile::new_display_p3())
+            .unwrap();
+        assert!(image.format().channel_count() >= 3);
+    }
+
+    #[test]
+    fn parses_built_in_display_profile_names() {
+        assert!(ColorManagement::display_profile_from_name("sRGB").is_ok());
+        assert!(ColorManagement::display_profile_from_name("p3").is_ok());
+        assert!(ColorManagement::display_profile_from_name("adobe-rgb").is_ok());
+        assert!(ColorManagement::display_profile_from_name("/nonexistent.icc").is_err());
+    }
+}
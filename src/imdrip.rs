@@ -1,58 +1,473 @@
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::SystemTime;
 
-use image::RgbaImage;
-use nalgebra::Vector2;
+use image::ImageFormat;
+use nalgebra::{Matrix4, Vector2, Vector3};
 
+use crate::color_management::{self, ColorManagement};
+use crate::opengl::material::definitions;
 use crate::opengl::material::textured::{TextureKind, TexturedMaterial};
+use crate::opengl::material::parameters::MaterialParameters;
 use crate::opengl::material::{Material, MockMaterial};
 use crate::opengl::mesh::Mesh;
+use crate::opengl::pbo::PboUploader;
+use crate::opengl::shader::binary_cache::ProgramBinaryCache;
+use crate::opengl::shader::builder::{ShaderProgramBuilder, ShaderStage};
+use crate::opengl::shader::preprocessor::ShaderPreprocessor;
+use crate::opengl::shader::registry::ShaderRegistry;
+use crate::opengl::shader::shader_program::ShaderProgram;
+use crate::opengl::shader::uniform_block::{BindingPoint, NamedUniformBlockBindings};
+use crate::opengl::resource_tracker;
+use crate::opengl::texture::cache::{CachedTexture, TextureCache};
+use crate::opengl::texture::loading;
+use crate::opengl::texture::texel_format::{ImageStatistics, TexelFormat, TextureImage};
 use crate::opengl::texture::texture_2d::Texture2D;
+use crate::opengl::texture::texture_cube_map::TextureCubeMap;
+use crate::opengl::texture::voxel::VoxelType;
+use crate::opengl::ubo::Ubo;
+use crate::panorama::{PanoramaCamera, PanoramaLayout, ViewMode};
+use crate::tone_mapping::{ToneMapping, ToneMappingOperator};
+use crate::volume::{MontageTile, SliceAxis, VolumeView};
+
+// The Display uniform block shared by all view shaders (see display.glsl)
+crate::std140_block! {
+    struct DisplayBlock {
+        exposure: f32,
+        gamma: f32,
+        tone_mapping_operator: i32,
+        tone_mapping_enabled: u32,
+        decode_srgb_samples: u32,
+        encode_linear_samples: u32,
+        linear_output: u32,
+        encode_output: u32,
+        window_size: Vector2<i32>,
+        _padding: [u8; 8],
+    }
+}
+
+const DISPLAY_BLOCK_BINDING: BindingPoint = 0;
+
+// Sampler uniforms of the view shaders
+const IMAGE_SAMPLER: &str = "image_texture";
+const VOLUME_SAMPLER: &str = "volume_texture";
+
+// Set for every panorama draw, the material holds an identity matrix
+const INVERSE_VIEW_PROJECTION: &str = "inverse_view_projection";
+
+crate::uniform_set! {
+    struct VolumeUniforms {
+        slice_axis: i32,
+        slice_position: f32,
+        slice_count: i32,
+        maximum_intensity_projection: bool,
+        intensity_range: Vector2<f32>,
+    }
+}
+
+crate::uniform_set! {
+    struct MontageUniforms {
+        slice_axis: i32,
+        intensity_range: Vector2<f32>,
+        tile_size: Vector2<f32>,
+    }
+}
+
+// Per-instance attributes of volume_montage.vert, one instance per slice
+crate::vertex_struct! {
+    struct MontageInstance {
+        #[location = 1]
+        tile_offset: Vector2<f32>,
+        #[location = 2]
+        tile_slice_position: f32,
+    }
+}
+
+impl From<MontageTile> for MontageInstance {
+    fn from(tile: MontageTile) -> Self {
+        Self {
+            tile_offset: tile.offset,
+            tile_slice_position: tile.slice_position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorInterpretation {
+    // sRGB-encoded images are decoded, blended in linear space and encoded again
+    Srgb,
+    // Stored values are shown as they are, e.g. for normal maps
+    Raw,
+}
+
+impl ColorInterpretation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorInterpretation::Srgb => "sRGB",
+            ColorInterpretation::Raw => "Raw",
+        }
+    }
+}
+
+// The encoded file is kept around, since ICC conversion happens while
+// decoding and toggling it requires decoding the image again
+struct EncodedImage {
+    bytes: Vec<u8>,
+    format_hint: Option<ImageFormat>,
+}
+
+// Cached textures are keyed by the modification time as well, so files that
+// changed in the meantime are decoded again
+#[derive(Debug, Clone, PartialEq)]
+struct ImageSource {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ImageSource {
+    fn from_path(path: &Path) -> Self {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let modified = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        Self { path, modified }
+    }
+}
+
+struct LoadedVolume {
+    material: TexturedMaterial,
+    montage_material: TexturedMaterial,
+    // Only built while the montage is shown, the tiles change with the axis
+    montage_mesh: Option<Mesh>,
+    view: VolumeView,
+    intensity_range: Vector2<f32>,
+}
+
+impl LoadedVolume {
+    fn update_montage_mesh(&mut self) {
+        if !self.view.montage() {
+            self.montage_mesh = None;
+            return;
+        }
+
+        let instances: Vec<MontageInstance> = self
+            .view
+            .montage_tiles()
+            .into_iter()
+            .map(MontageInstance::from)
+            .collect();
+        let mesh = crate::opengl::mesh::factory::create_instanced_quad_mesh(
+            Rc::new(MockMaterial),
+            &instances,
+        )
+        .expect("Montage instances only use attribute indices");
+        self.montage_mesh = Some(mesh);
+    }
+}
+
+fn build_view_shader(
+    preprocessor: &ShaderPreprocessor,
+    block_bindings: &NamedUniformBlockBindings,
+    vert_name: &str,
+    frag_name: &str,
+) -> ShaderProgram {
+    let vert_source = preprocessor
+        .process_embedded(vert_name)
+        .expect("Failed to preprocess vertex shader");
+    let frag_source = preprocessor
+        .process_embedded(frag_name)
+        .expect("Failed to preprocess fragment shader");
+
+    let (shader, cache_errors) = ShaderProgramBuilder::new()
+        .add_preprocessed_source(ShaderStage::Vertex, vert_source)
+        .add_preprocessed_source(ShaderStage::Fragment, frag_source)
+        .use_binary_cache(ProgramBinaryCache::from_xdg_cache_dir())
+        .build_with_cache_errors()
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    // The shader was built from source, so cache problems aren't fatal
+    for err in cache_errors.iter() {
+        println!("{}", err);
+    }
+
+    shader.set_uniform_block_bindings(block_bindings);
+    shader
+}
+
+// Custom display modes are materials from a definition file (see
+// opengl::material::definitions), the image is bound to IMAGE_SAMPLER
+fn display_modes_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(ImdripCtx::DISPLAY_MODES_ENV_VAR) {
+        return Some(PathBuf::from(path));
+    }
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("imdrip").join("display_modes.toml"))
+        .filter(|path| path.is_file())
+}
+
+fn load_display_modes(
+    preprocessor: &ShaderPreprocessor,
+    block_bindings: &NamedUniformBlockBindings,
+    shader_registry: &ShaderRegistry,
+) -> Vec<(String, TexturedMaterial)> {
+    let Some(path) = display_modes_path() else {
+        return Vec::new();
+    };
+
+    match definitions::load_definitions(&path, preprocessor, shader_registry) {
+        Ok(definitions) => {
+            // The shaders were built from source, so these aren't fatal
+            for warning in definitions.warnings.iter() {
+                println!("{}", warning);
+            }
+            for (_, shader) in definitions.shaders.iter() {
+                shader.set_uniform_block_bindings(block_bindings);
+            }
+            definitions.materials
+        }
+        Err(err) => {
+            println!("Failed to load display modes: {}", err);
+            Vec::new()
+        }
+    }
+}
 
 pub struct ImdripCtx {
     material: TexturedMaterial,
+    // Drawn instead of the material above if a custom display mode is chosen
+    display_modes: Vec<(String, TexturedMaterial)>,
+    display_mode_index: Option<usize>,
+    // Set while the shown texture was loaded from a file
+    current_source: Option<ImageSource>,
+    // Textures of previously shown files, kept within the texture budget
+    texture_cache: TextureCache<ImageSource>,
     current_image_size: Vector2<i32>,
+    current_image_statistics: Option<ImageStatistics>,
+    current_profile_name: Option<String>,
+    current_encoded_image: Option<EncodedImage>,
     current_window_size: Vector2<i32>,
     resize_on_load: bool,
+    tone_mapping: ToneMapping,
+    color_interpretation: ColorInterpretation,
+    color_management: ColorManagement,
+    srgb_framebuffer: bool,
+    display_buffer: Ubo,
+    // Re-uploads go through pixel buffer objects if set
+    upload_buffer: Option<PboUploader>,
+    view_mode: ViewMode,
+    panorama_camera: PanoramaCamera,
+    panorama_layout_override: Option<PanoramaLayout>,
+    equirectangular_shader: Rc<ShaderProgram>,
+    cube_map_shader: Rc<ShaderProgram>,
+    // Only built while a horizontal cross is shown as a panorama
+    panorama_cube_map: Option<Rc<TextureCubeMap>>,
+    panorama_material: Option<TexturedMaterial>,
+    volume_shader: Rc<ShaderProgram>,
+    volume_montage_shader: Rc<ShaderProgram>,
+    // Shown instead of the image while set
+    volume: Option<LoadedVolume>,
     mesh: Mesh,
 }
 
 impl ImdripCtx {
+    pub const TEXTURE_BUDGET_ENV_VAR: &'static str = "IMDRIP_TEXTURE_BUDGET_MB";
+    const DEFAULT_TEXTURE_BUDGET_MB: usize = 512;
+    pub const DISPLAY_MODES_ENV_VAR: &'static str = "IMDRIP_DISPLAY_MODES";
+
     pub fn new(current_window_size: Vector2<i32>) -> Self {
-        let texture_draw_shader = crate::opengl::shader::create_shader_from_parts(
-            &include_str!("shaders/quad.vert"),
-            &include_str!("shaders/quad.frag"),
+        let texture_budget_mb = std::env::var(Self::TEXTURE_BUDGET_ENV_VAR)
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(Self::DEFAULT_TEXTURE_BUDGET_MB);
+        resource_tracker::set_texture_budget(Some(texture_budget_mb * 1024 * 1024));
+
+        let mut preprocessor = crate::opengl::shader::create_default_preprocessor();
+        for operator in ToneMappingOperator::ALL.iter() {
+            preprocessor.define(operator.define_name(), &operator.shader_index().to_string());
+        }
+        for axis in SliceAxis::ALL.iter() {
+            preprocessor.define(axis.define_name(), &axis.shader_index().to_string());
+        }
+
+        let block_bindings =
+            NamedUniformBlockBindings::from([(String::from("Display"), DISPLAY_BLOCK_BINDING)]);
+        let texture_draw_shader = Rc::new(build_view_shader(
+            &preprocessor,
+            &block_bindings,
+            "quad.vert",
+            "quad.frag",
+        ));
+        let material = TexturedMaterial::new(Rc::clone(&texture_draw_shader), vec![]);
+
+        // Display modes can reuse the built-in image shader with other parameters
+        let mut shader_registry = ShaderRegistry::new();
+        shader_registry.insert("image", texture_draw_shader);
+        let display_modes = load_display_modes(&preprocessor, &block_bindings, &shader_registry);
+
+        let volume_shader =
+            build_view_shader(&preprocessor, &block_bindings, "quad.vert", "volume.frag");
+        let equirectangular_shader =
+            build_view_shader(&preprocessor, &block_bindings, "quad.vert", "panorama.frag");
+        preprocessor.define(PanoramaLayout::CUBE_MAP_DEFINE, "1");
+        let cube_map_shader =
+            build_view_shader(&preprocessor, &block_bindings, "quad.vert", "panorama.frag");
+        preprocessor.define(VolumeView::MONTAGE_DEFINE, "1");
+        let volume_montage_shader = build_view_shader(
+            &preprocessor,
+            &block_bindings,
+            "volume_montage.vert",
+            "volume.frag",
         );
-        texture_draw_shader.set_int("image_texture", 0);
-        let material = TexturedMaterial::new(Rc::new(texture_draw_shader), vec![]);
+
+        let color_management = ColorManagement::from_env().unwrap_or_else(|err| {
+            println!("{}, falling back to sRGB", err);
+            ColorManagement::srgb()
+        });
 
         let mesh = crate::opengl::mesh::factory::create_basic_quad_mesh(Rc::new(MockMaterial), 1.0);
 
         Self {
             material,
+            display_modes,
+            display_mode_index: None,
+            current_source: None,
+            texture_cache: TextureCache::new(),
             current_image_size: Vector2::new(0, 0),
+            current_image_statistics: None,
+            current_profile_name: None,
+            current_encoded_image: None,
             current_window_size,
             resize_on_load: true,
+            tone_mapping: ToneMapping::new(),
+            color_interpretation: ColorInterpretation::Srgb,
+            color_management,
+            srgb_framebuffer: crate::opengl::context::default_framebuffer_is_srgb(),
+            display_buffer: Ubo::new(),
+            upload_buffer: None,
+            view_mode: ViewMode::Flat,
+            panorama_camera: PanoramaCamera::new(),
+            panorama_layout_override: None,
+            equirectangular_shader: Rc::new(equirectangular_shader),
+            cube_map_shader: Rc::new(cube_map_shader),
+            panorama_cube_map: None,
+            panorama_material: None,
+            volume_shader: Rc::new(volume_shader),
+            volume_montage_shader: Rc::new(volume_montage_shader),
+            volume: None,
             mesh,
         }
     }
 
-    pub fn draw(&self) {
-        crate::opengl::texture::set_active_texture_unit(0).unwrap();
-        self.mesh.draw_with_material(&self.material, |_| {
-            let shader = self.material.shader_program();
-            shader.set_vec2i("window_size", self.current_window_size);
-        });
+    pub fn draw(&mut self) {
+        if let Some(uploader) = self.upload_buffer.as_mut() {
+            uploader.flush();
+        }
+
+        let srgb_output = self.color_interpretation == ColorInterpretation::Srgb;
+        let srgb_encoded = self
+            .texel_format()
+            .is_some_and(|format| format.is_srgb_encoded());
+        let decoded_by_sampler = self
+            .texel_format()
+            .is_some_and(|format| format.is_decoded_by_sampler());
+
+        let display_block = DisplayBlock {
+            exposure: self.tone_mapping.exposure(),
+            gamma: self.tone_mapping.gamma(),
+            tone_mapping_operator: self.tone_mapping.operator().shader_index(),
+            tone_mapping_enabled: self.tone_mapping.enabled() as u32,
+            decode_srgb_samples: (srgb_output && srgb_encoded && !decoded_by_sampler) as u32,
+            encode_linear_samples: (!srgb_output && decoded_by_sampler) as u32,
+            linear_output: srgb_output as u32,
+            encode_output: (srgb_output && !self.srgb_framebuffer) as u32,
+            window_size: self.current_window_size,
+            _padding: [0; 8],
+        };
+        self.display_buffer.bind();
+        self.display_buffer.copy_block(&display_block, gl::DYNAMIC_DRAW);
+        self.display_buffer.bind_base(DISPLAY_BLOCK_BINDING);
+
+        crate::opengl::context::set_framebuffer_srgb_enabled(srgb_output && self.srgb_framebuffer);
+
+        if let Some(LoadedVolume {
+            montage_material,
+            montage_mesh: Some(montage_mesh),
+            view,
+            intensity_range,
+            ..
+        }) = &self.volume
+        {
+            montage_mesh.draw_with_material(montage_material, |_| {
+                let shader = montage_material.shader_program();
+                shader.set_uniforms(&MontageUniforms {
+                    slice_axis: view.axis().shader_index(),
+                    intensity_range: *intensity_range,
+                    tile_size: view.montage_tile_size(),
+                });
+            });
+            return;
+        }
+
+        if let Some(volume) = &self.volume {
+            self.mesh.draw_with_material(&volume.material, |_| {
+                let shader = volume.material.shader_program();
+                shader.set_uniforms(&VolumeUniforms {
+                    slice_axis: volume.view.axis().shader_index(),
+                    slice_position: volume.view.slice_position(),
+                    slice_count: volume.view.slice_count(),
+                    maximum_intensity_projection: volume.view.maximum_intensity_projection(),
+                    intensity_range: volume.intensity_range,
+                });
+            });
+            return;
+        }
+
+        if let (ViewMode::Panorama, Some(material)) = (self.view_mode, &self.panorama_material) {
+            let aspect_ratio =
+                self.current_window_size.x as f32 / self.current_window_size.y.max(1) as f32;
+            let inverse_view_projection = self.panorama_camera.inverse_view_projection(aspect_ratio);
+
+            let overrides =
+                MaterialParameters::new().with(INVERSE_VIEW_PROJECTION, inverse_view_projection);
+            self.mesh.draw_with_parameters(material, &overrides, |_| {});
+            return;
+        }
+
+        let material = match self.display_mode_index {
+            Some(index) => &self.display_modes[index].1,
+            None => &self.material,
+        };
+        self.mesh.draw_with_material(material, |_| {});
+    }
+
+    // Has to be called whenever the image texture or the display mode changes
+    fn update_display_mode_texture(&mut self) {
+        let Some(index) = self.display_mode_index else {
+            return;
+        };
+
+        let image_texture = self.material.texture(IMAGE_SAMPLER).cloned();
+        let (name, material) = &mut self.display_modes[index];
+        let Some(texture) = image_texture else {
+            material.remove_texture(IMAGE_SAMPLER);
+            return;
+        };
+        if let Err(err) = material.set_texture(IMAGE_SAMPLER, texture) {
+            println!("Failed to show display mode {}: {}", name, err);
+            self.display_mode_index = None;
+        }
     }
 
     fn has_textures(&self) -> bool {
-        let textures = self.material.textures();
-        textures.len() > 0
+        self.material.texture(IMAGE_SAMPLER).is_some()
     }
 
     fn get_texture(&self) -> Option<&Rc<Texture2D>> {
-        let textures = self.material.textures();
-        if let TextureKind::TwoDimensional { texture } = textures.get(0)? {
+        if let TextureKind::TwoDimensional { texture, .. } = self.material.texture(IMAGE_SAMPLER)? {
             Some(texture)
         } else {
             None
@@ -63,78 +478,278 @@ impl ImdripCtx {
         self.current_window_size = window_size;
     }
 
-    fn update_existing_texture_from_path<P: AsRef<Path>>(&mut self, path: P) {
-        if let Some(tex) = self.get_texture() {
-            let load_result =
-                crate::opengl::texture::loading::load_into_texture_from_path(&path, tex.as_ref());
+    pub fn update_texture_from_path<P: AsRef<Path>>(&mut self, path: P) {
+        let source = ImageSource::from_path(path.as_ref());
+        if let Some(cached) = self.texture_cache.remove(&source) {
+            self.cache_current_texture();
+            self.show_cached_texture(source, cached);
+            return;
+        }
 
-            if load_result.is_err() {
-                println!("Failed to load texture: {}", load_result.unwrap_err());
-                return;
-            }
+        // Reloading the same file updates the texture in place, other files
+        // get their own texture so that the current one can be cached
+        let same_path = self
+            .current_source
+            .as_ref()
+            .map_or(false, |current| current.path == source.path);
+        if !same_path {
+            self.cache_current_texture();
+        }
 
-            let size = load_result.unwrap();
-            self.current_image_size = size;
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let format_hint = ImageFormat::from_path(&path).ok();
+                if self.update_texture_from_encoded_image(EncodedImage { bytes, format_hint }) {
+                    self.current_source = Some(source);
+                }
+            }
+            Err(err) => println!("Failed to load texture: {}", err),
         }
     }
 
-    fn load_new_texture_from_path<P: AsRef<Path>>(&mut self, path: P) {
-        let load_result = crate::opengl::texture::loading::create_and_load_texture_from_path(&path);
-        if load_result.is_err() {
-            println!("Failed to load texture: {}", load_result.unwrap_err());
+    fn cache_current_texture(&mut self) {
+        let Some(source) = self.current_source.take() else {
             return;
+        };
+
+        let removed = self.material.remove_texture(IMAGE_SAMPLER);
+        if let Some(TextureKind::TwoDimensional { texture, format }) = removed {
+            self.texture_cache.insert(source, CachedTexture { texture, format });
         }
+        self.update_display_mode_texture();
+    }
 
-        // If the texture was loaded successfully, store it in the
-        // material used for drawing
-        let (texture, size) = load_result.unwrap();
-        let stored_texture = TextureKind::TwoDimensional { texture };
+    fn show_cached_texture(&mut self, source: ImageSource, cached: CachedTexture) {
+        let CachedTexture { texture, format } = cached;
+        let Some(allocation) = texture.allocation() else {
+            return;
+        };
 
-        let textures = self.material.textures_mut();
-        textures.push(stored_texture);
+        println!(
+            "Showing cached {}x{} {} texture",
+            allocation.width,
+            allocation.height,
+            format.name()
+        );
 
-        self.current_image_size = size;
+        self.volume = None;
+        self.panorama_cube_map = None;
+        self.current_image_size = Vector2::new(allocation.width, allocation.height);
+        self.current_image_statistics = None;
+        self.current_profile_name = None;
+        self.current_encoded_image = None;
+        self.current_source = Some(source);
+
+        self.set_image_texture(TextureKind::TwoDimensional { texture, format });
+
+        self.refresh_panorama();
     }
 
-    pub fn update_texture_from_path<P: AsRef<Path>>(&mut self, path: P) {
-        if self.has_textures() {
-            self.update_existing_texture_from_path(path);
-            return;
+    fn update_texture_from_encoded_image(&mut self, encoded_image: EncodedImage) -> bool {
+        let EncodedImage { bytes, format_hint } = &encoded_image;
+        let decode_result = loading::decode_texture_image(bytes, *format_hint);
+        let mut image = match decode_result {
+            Ok(image) => image,
+            Err(err) => {
+                println!("Failed to load texture: {}", err);
+                return false;
+            }
+        };
+
+        // Convert from the embedded ICC profile (if there is one) to the display profile
+        let embedded_profile = loading::guess_image_format(bytes, *format_hint)
+            .and_then(|format| color_management::read_embedded_profile(bytes, format));
+
+        let mut profile_name = None;
+        if let Some(icc_data) = embedded_profile {
+            match color_management::parse_profile(&icc_data) {
+                Ok(profile) => {
+                    profile_name = Some(color_management::profile_name(&profile));
+                    if self.color_management.enabled() {
+                        if let Err(err) = self.color_management.convert(&mut image, &profile) {
+                            println!("Failed to apply embedded ICC profile: {}", err);
+                        }
+                    }
+                }
+                Err(err) => println!("Ignoring embedded ICC profile: {}", err),
+            }
         }
 
-        self.load_new_texture_from_path(path);
+        image.flip_vertical();
+        self.update_texture_from_image(image);
+
+        self.current_profile_name = profile_name;
+        self.current_encoded_image = Some(encoded_image);
+        true
     }
 
-    fn update_existing_texture_from_image(&mut self, image: RgbaImage) {
-        if let Some(tex) = self.get_texture() {
-            let size =
-                crate::opengl::texture::loading::load_from_image_into_texture(image, tex.as_ref());
+    fn update_existing_texture_from_image(&mut self, image: TextureImage) {
+        let format = image.format();
+        let Some(tex) = self.get_texture().cloned() else {
+            return;
+        };
 
-            self.current_image_size = size;
+        // Immutable storage can't be resized, so a new texture is created instead
+        let size = image.size();
+        if !tex.can_store(size.x, size.y, format.storage_format()) {
+            self.material.remove_texture(IMAGE_SAMPLER);
+            self.load_new_texture_from_image(image);
+            return;
         }
+
+        // Staged images are uploaded when the next frame is drawn
+        let upload_result = match self.upload_buffer.as_mut() {
+            Some(uploader) => uploader.stage(image, &tex).map(|_| size),
+            None => loading::load_from_image_into_texture(image, &tex),
+        };
+
+        match upload_result {
+            Ok(size) => self.current_image_size = size,
+            Err(err) => {
+                println!("Failed to update texture: {}", err);
+                return;
+            }
+        }
+
+        if let Some(TextureKind::TwoDimensional {
+            format: stored_format,
+            ..
+        }) = self.material.texture_mut(IMAGE_SAMPLER)
+        {
+            *stored_format = format;
+        }
+        self.update_display_mode_texture();
     }
 
-    fn load_new_texture_from_image(&mut self, image: RgbaImage) {
+    fn load_new_texture_from_image(&mut self, image: TextureImage) {
+        let format = image.format();
         let image = crate::opengl::texture::loading::create_from_image(image);
 
         // If the texture was loaded successfully, store it in the
         // material used for drawing
         let (texture, size) = image;
-        let stored_texture = TextureKind::TwoDimensional { texture };
-
-        let textures = self.material.textures_mut();
-        textures.push(stored_texture);
+        let stored_texture = TextureKind::TwoDimensional { texture, format };
+        self.set_image_texture(stored_texture);
 
         self.current_image_size = size;
     }
 
-    pub fn update_texture_from_image(&mut self, image: RgbaImage) {
+    fn set_image_texture(&mut self, texture: TextureKind) {
+        self.material
+            .set_texture(IMAGE_SAMPLER, texture)
+            .unwrap_or_else(|err| panic!("{}", err));
+        self.update_display_mode_texture();
+    }
+
+    pub fn update_texture_from_image(&mut self, image: TextureImage) {
+        self.volume = None;
+        self.panorama_cube_map = None;
+        if self.needs_panorama_cube_map(image.size()) {
+            self.update_panorama_cube_map(&image);
+        }
+
+        let statistics = image.statistics();
+        println!(
+            "Loaded {}x{} {} image (min {:?}, max {:?}, mean {:?})",
+            image.size().x,
+            image.size().y,
+            image.format().name(),
+            statistics.min,
+            statistics.max,
+            statistics.mean
+        );
+        self.current_image_statistics = Some(statistics);
+        self.current_profile_name = None;
+        self.current_encoded_image = None;
+
         if self.has_textures() {
             self.update_existing_texture_from_image(image);
+        } else {
+            self.load_new_texture_from_image(image);
+        }
+
+        self.update_panorama_material();
+        self.texture_cache.evict_to_budget();
+    }
+
+    fn panorama_layout_for(&self, image_size: Vector2<i32>) -> PanoramaLayout {
+        self.panorama_layout_override
+            .or_else(|| PanoramaLayout::detect(image_size))
+            .unwrap_or(PanoramaLayout::Equirectangular)
+    }
+
+    fn needs_panorama_cube_map(&self, image_size: Vector2<i32>) -> bool {
+        self.view_mode == ViewMode::Panorama
+            && self.panorama_layout_for(image_size) == PanoramaLayout::HorizontalCross
+    }
+
+    // Images are flipped for 2D textures, cube map faces are expected top row first
+    fn update_panorama_cube_map(&mut self, image: &TextureImage) {
+        let mut cross_image = image.clone();
+        cross_image.flip_vertical();
+
+        match loading::create_cube_map_from_horizontal_cross(&cross_image) {
+            Ok(loaded) => self.panorama_cube_map = Some(loaded.texture),
+            Err(err) => println!("Failed to create cube map: {}", err),
+        }
+    }
+
+    fn update_panorama_material(&mut self) {
+        self.panorama_material = None;
+        if self.view_mode != ViewMode::Panorama {
+            return;
+        }
+
+        let Some(format) = self.texel_format() else {
             return;
+        };
+
+        let parameters =
+            MaterialParameters::new().with(INVERSE_VIEW_PROJECTION, Matrix4::<f32>::identity());
+        let material = match self.panorama_layout() {
+            PanoramaLayout::Equirectangular => {
+                let Some(texture) = self.get_texture().cloned() else {
+                    return;
+                };
+                TexturedMaterial::with_parameters(
+                    Rc::clone(&self.equirectangular_shader),
+                    vec![(IMAGE_SAMPLER, TextureKind::TwoDimensional { texture, format })],
+                    parameters,
+                )
+            }
+            PanoramaLayout::HorizontalCross => {
+                let Some(texture) = self.panorama_cube_map.clone() else {
+                    return;
+                };
+                TexturedMaterial::with_parameters(
+                    Rc::clone(&self.cube_map_shader),
+                    vec![(IMAGE_SAMPLER, TextureKind::CubeMap { texture, format })],
+                    parameters,
+                )
+            }
+        };
+        self.panorama_material = Some(material);
+    }
+
+    // The cube map is built from the decoded image, which isn't kept around.
+    // Cached textures have no encoded image, so they're read back instead.
+    // The cube map is built from the texels of the shown texture, so the
+    // image doesn't have to be decoded again
+    fn refresh_panorama(&mut self) {
+        if self.panorama_cube_map.is_none() && self.needs_panorama_cube_map(self.current_image_size)
+        {
+            // A staged upload would otherwise be missing from the readback
+            if let Some(uploader) = self.upload_buffer.as_mut() {
+                uploader.flush();
+            }
+            match self.get_texture().map(|texture| texture.read_image(0)) {
+                Some(Ok(image)) => self.update_panorama_cube_map(&image),
+                Some(Err(err)) => println!("Failed to read back the texture: {}", err),
+                None => {}
+            }
         }
 
-        self.load_new_texture_from_image(image);
+        self.update_panorama_material();
     }
 
     pub fn handle_file_path(&mut self, path: &PathBuf) -> bool {
@@ -165,24 +780,149 @@ impl ImdripCtx {
         let received_bytes = bytes.unwrap();
         println!("Received {} bytes", received_bytes.len());
 
-        let image = image::load_from_memory(&received_bytes);
-        if let Err(error) = image {
-            println!("Failed to create image from response: {}", error);
+        // Downloaded images aren't cached, but the current file might be
+        self.cache_current_texture();
+
+        let encoded_image = EncodedImage {
+            bytes: received_bytes.to_vec(),
+            format_hint: None,
+        };
+        if !self.update_texture_from_encoded_image(encoded_image) {
             return false;
         }
 
         println!("Done loading image from URL!");
-        let image = image.unwrap();
-        let flipped_image = image::imageops::flip_vertical(&image);
-        self.update_texture_from_image(flipped_image);
+        true
+    }
 
+    pub fn load_volume_from_path<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        dimensions: Vector3<i32>,
+        voxel_type: VoxelType,
+    ) -> bool {
+        let loaded = loading::create_3d_from_raw_voxel_path(&path, dimensions, voxel_type);
+        let (loaded, (min, max)) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                println!("Failed to load volume: {}", err);
+                return false;
+            }
+        };
+
+        println!(
+            "Loaded {}x{}x{} {} volume (min {}, max {})",
+            loaded.size.x,
+            loaded.size.y,
+            loaded.size.z,
+            loaded.format.name(),
+            min,
+            max
+        );
+
+        let texture = TextureKind::ThreeDimensional {
+            texture: loaded.texture,
+            format: loaded.format,
+        };
+        let material = TexturedMaterial::new(
+            Rc::clone(&self.volume_shader),
+            vec![(VOLUME_SAMPLER, texture.clone())],
+        );
+        let montage_material = TexturedMaterial::new(
+            Rc::clone(&self.volume_montage_shader),
+            vec![(VOLUME_SAMPLER, texture)],
+        );
+        let view = VolumeView::new(dimensions);
+        self.current_image_size = view.slice_size();
+        self.current_image_statistics = None;
+        self.current_profile_name = None;
+        self.current_encoded_image = None;
+        self.volume = Some(LoadedVolume {
+            material,
+            montage_material,
+            montage_mesh: None,
+            view,
+            intensity_range: Vector2::new(min, max),
+        });
         true
     }
 
+    pub fn volume_view(&self) -> Option<&VolumeView> {
+        self.volume.as_ref().map(|volume| &volume.view)
+    }
+
+    pub fn volume_view_mut(&mut self) -> Option<&mut VolumeView> {
+        self.volume.as_mut().map(|volume| &mut volume.view)
+    }
+
+    // The slice size changes with the axis, so the image size has to follow
+    pub fn cycle_slice_axis(&mut self) {
+        if let Some(volume) = self.volume.as_mut() {
+            volume.view.cycle_axis();
+            volume.update_montage_mesh();
+            self.current_image_size = volume.view.slice_size();
+        }
+    }
+
+    pub fn toggle_volume_montage(&mut self) {
+        if let Some(volume) = self.volume.as_mut() {
+            volume.view.toggle_montage();
+            volume.update_montage_mesh();
+        }
+    }
+
     pub fn image_size(&self) -> Vector2<i32> {
         self.current_image_size
     }
 
+    pub fn texel_format(&self) -> Option<TexelFormat> {
+        let texture = match &self.volume {
+            Some(volume) => volume.material.texture(VOLUME_SAMPLER),
+            None => self.material.texture(IMAGE_SAMPLER),
+        };
+        texture.map(|texture| texture.format())
+    }
+
+    pub fn image_statistics(&self) -> Option<&ImageStatistics> {
+        self.current_image_statistics.as_ref()
+    }
+
+    // The quad covers the whole window, so the cursor position maps directly
+    // onto the image. The texture is read back since the decoded image isn't
+    // kept around, and the texel is reported with the top row as y = 0.
+    pub fn print_texel_at(&self, cursor_position: Vector2<f32>) {
+        if self.view_mode != ViewMode::Flat || self.volume.is_some() {
+            println!("Texels can only be inspected in the flat image view");
+            return;
+        }
+
+        let window_size = self.current_window_size.cast::<f32>();
+        let image_size = self.current_image_size;
+        if window_size.x <= 0.0 || window_size.y <= 0.0 {
+            return;
+        }
+        let x = (cursor_position.x / window_size.x * image_size.x as f32).floor();
+        let y = (cursor_position.y / window_size.y * image_size.y as f32).floor();
+        if x < 0.0 || y < 0.0 || x >= image_size.x as f32 || y >= image_size.y as f32 {
+            return;
+        }
+        let (x, y) = (x as u32, y as u32);
+
+        let image = match self.get_texture().map(|texture| texture.read_image(0)) {
+            Some(Ok(image)) => image,
+            Some(Err(err)) => {
+                println!("Failed to read back the texture: {}", err);
+                return;
+            }
+            None => return,
+        };
+
+        let texture_row = image_size.y as u32 - 1 - y;
+        if let Some([r, g, b, a]) = image.texel(x, texture_row) {
+            println!("Texel ({}, {}): R {} G {} B {} A {}", x, y, r, g, b, a);
+        }
+    }
+
     pub fn set_resize_on_load(&mut self, resize_on_load: bool) {
         self.resize_on_load = resize_on_load;
     }
@@ -194,4 +934,139 @@ impl ImdripCtx {
     pub fn resize_on_load(&self) -> bool {
         self.resize_on_load
     }
+
+    pub fn pbo_uploads(&self) -> bool {
+        self.upload_buffer.is_some()
+    }
+
+    pub fn toggle_pbo_uploads(&mut self) {
+        self.upload_buffer = match self.upload_buffer.take() {
+            Some(mut uploader) => {
+                uploader.flush();
+                None
+            }
+            None => Some(PboUploader::new()),
+        };
+    }
+
+    pub fn view_mode(&self) -> ViewMode {
+        self.view_mode
+    }
+
+    pub fn toggle_view_mode(&mut self) {
+        self.view_mode = match self.view_mode {
+            ViewMode::Flat => ViewMode::Panorama,
+            ViewMode::Panorama => ViewMode::Flat,
+        };
+        self.refresh_panorama();
+    }
+
+    pub fn display_mode_name(&self) -> Option<&str> {
+        self.display_mode_index
+            .map(|index| self.display_modes[index].0.as_str())
+    }
+
+    pub fn has_display_modes(&self) -> bool {
+        !self.display_modes.is_empty()
+    }
+
+    // Cycles through the custom display modes and back to the default one
+    pub fn cycle_display_mode(&mut self) {
+        self.display_mode_index = match self.display_mode_index {
+            None if !self.display_modes.is_empty() => Some(0),
+            Some(index) if index + 1 < self.display_modes.len() => Some(index + 1),
+            _ => None,
+        };
+        self.update_display_mode_texture();
+    }
+
+    pub fn panorama_layout(&self) -> PanoramaLayout {
+        self.panorama_layout_for(self.current_image_size)
+    }
+
+    pub fn cycle_panorama_layout_override(&mut self) {
+        self.panorama_layout_override = PanoramaLayout::next_override(self.panorama_layout_override);
+        self.refresh_panorama();
+    }
+
+    pub fn panorama_camera(&self) -> &PanoramaCamera {
+        &self.panorama_camera
+    }
+
+    pub fn panorama_camera_mut(&mut self) -> &mut PanoramaCamera {
+        &mut self.panorama_camera
+    }
+
+    pub fn panorama_status(&self) -> String {
+        let detection = if self.panorama_layout_override.is_some() {
+            "forced"
+        } else if PanoramaLayout::detect(self.current_image_size).is_some() {
+            "detected"
+        } else {
+            "assumed"
+        };
+
+        format!(
+            "{} ({}), {}",
+            self.panorama_layout().name(),
+            detection,
+            self.panorama_camera.status()
+        )
+    }
+
+    pub fn tone_mapping(&self) -> &ToneMapping {
+        &self.tone_mapping
+    }
+
+    pub fn tone_mapping_mut(&mut self) -> &mut ToneMapping {
+        &mut self.tone_mapping
+    }
+
+    pub fn color_interpretation(&self) -> ColorInterpretation {
+        self.color_interpretation
+    }
+
+    pub fn toggle_color_interpretation(&mut self) {
+        self.color_interpretation = match self.color_interpretation {
+            ColorInterpretation::Srgb => ColorInterpretation::Raw,
+            ColorInterpretation::Raw => ColorInterpretation::Srgb,
+        };
+    }
+
+    pub fn color_management_status(&self) -> String {
+        let profile_name = match &self.current_profile_name {
+            Some(profile_name) => profile_name.as_str(),
+            None => return String::from("No ICC profile"),
+        };
+
+        if self.color_management.enabled() {
+            format!(
+                "{} -> {}",
+                profile_name,
+                self.color_management.display_profile_name()
+            )
+        } else {
+            format!("{} (not converted)", profile_name)
+        }
+    }
+
+    pub fn memory_status(&self) -> String {
+        format!(
+            "{}, {} cached textures",
+            resource_tracker::summary(),
+            self.texture_cache.len()
+        )
+    }
+
+    pub fn toggle_color_management(&mut self) {
+        self.color_management.toggle_enabled();
+
+        // Cached textures were converted with the previous setting
+        self.texture_cache.clear();
+
+        // Conversion happens while decoding, so the image has to be decoded again
+        if let Some(encoded_image) = self.current_encoded_image.take() {
+            self.update_texture_from_encoded_image(encoded_image);
+        }
+    }
 }
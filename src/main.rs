@@ -1,13 +1,21 @@
 extern crate gl;
 extern crate glfw;
 
+mod color_management;
 mod imdrip;
 mod opengl;
+mod panorama;
+mod tone_mapping;
+mod volume;
 
 use glfw::Context;
 use nalgebra::Vector2;
 
 use imdrip::ImdripCtx;
+use opengl::resource_tracker;
+use opengl::texture::voxel::VoxelType;
+use panorama::ViewMode;
+use tone_mapping::ToneMapping;
 
 fn main() {
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
@@ -16,6 +24,7 @@ fn main() {
     glfw.window_hint(glfw::WindowHint::OpenGlProfile(
         glfw::OpenGlProfileHint::Core,
     ));
+    glfw.window_hint(glfw::WindowHint::SRgbCapable(true));
     let (mut window, events) = glfw
         .create_window(512, 512, "imdrip", glfw::WindowMode::Windowed)
         .expect("Failed to create GLFW window.");
@@ -23,6 +32,9 @@ fn main() {
     window.set_size_polling(true);
     window.set_key_polling(true);
     window.set_drag_and_drop_polling(true);
+    window.set_scroll_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_cursor_pos_polling(true);
 
     window.make_current();
     glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
@@ -47,6 +59,45 @@ fn main() {
                     "{} <file path/url> - Load an image from disk/url",
                     executable_name
                 );
+                println!(
+                    "{} volume <raw file> <W>x<H>x<D> <u8|u16|f32> - Load a raw voxel volume",
+                    executable_name
+                );
+                println!();
+                println!("Keys:");
+                println!("E / Shift+E       - Increase/decrease exposure (or Ctrl+scroll)");
+                println!("G / Shift+G       - Increase/decrease gamma (or Shift+scroll)");
+                println!("T                 - Cycle tone mapping operator");
+                println!("L                 - Toggle tone mapping/linear clamp");
+                println!("0                 - Reset exposure, gamma, tone mapping and the camera");
+                println!("S                 - Toggle sRGB/raw color interpretation");
+                println!("C                 - Toggle ICC profile conversion");
+                println!("P                 - Toggle pixel buffer object uploads");
+                println!("V                 - Toggle flat/panorama view (drag to look around,");
+                println!("                    scroll to zoom)");
+                println!("M                 - Cycle panorama layout (auto, equirect, cross)");
+                println!("A                 - Cycle volume slice axis");
+                println!("Up / Down         - Move through volume slices (or scroll)");
+                println!("I                 - Toggle volume maximum intensity projection");
+                println!("O                 - Toggle volume slice montage");
+                println!("B                 - Print GPU memory usage");
+                println!("D                 - Cycle custom display modes");
+                println!("Right click       - Print the texel values under the cursor");
+                println!();
+                println!(
+                    "Set {} to srgb, display-p3, adobe-rgb or the path of an ICC profile",
+                    color_management::ColorManagement::DISPLAY_PROFILE_ENV_VAR
+                );
+                println!("to choose the display profile images are converted to.");
+                println!(
+                    "Set {} to the texture memory budget in MiB (512 by default).",
+                    ImdripCtx::TEXTURE_BUDGET_ENV_VAR
+                );
+                println!(
+                    "Set {} to a display mode definition file",
+                    ImdripCtx::DISPLAY_MODES_ENV_VAR
+                );
+                println!("(~/.config/imdrip/display_modes.toml by default).");
                 return;
             }
 
@@ -59,8 +110,36 @@ fn main() {
                 window.set_size(size.x, size.y);
             }
         }
+    } else if args.len() == 5 {
+        let arguments: Vec<String> = args.skip(1).collect();
+        if arguments[0] != "volume" {
+            println!("Unknown command {}, see help", arguments[0]);
+            return;
+        }
+
+        let dimensions = match volume::parse_dimensions(&arguments[2]) {
+            Ok(dimensions) => dimensions,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        let Some(voxel_type) = VoxelType::from_name(&arguments[3]) else {
+            println!("Unknown voxel type {}, expected u8, u16 or f32", arguments[3]);
+            return;
+        };
+
+        let successfully_loaded =
+            drawing_ctx.load_volume_from_path(&arguments[1], dimensions, voxel_type);
+        if successfully_loaded && drawing_ctx.resize_on_load() {
+            let size = drawing_ctx.image_size();
+            window.set_size(size.x, size.y);
+        }
     }
 
+    // Last cursor position while dragging the panorama around
+    let mut drag_position: Option<Vector2<f32>> = None;
+
     // Main loop
     while !window.should_close() {
         unsafe {
@@ -101,6 +180,177 @@ fn main() {
                     window.set_size(size.x, size.y);
                     println!("Resized window to {}, {} to fit image", size.x, size.y);
                 }
+                glfw::WindowEvent::Key(
+                    glfw::Key::E,
+                    _,
+                    glfw::Action::Press | glfw::Action::Repeat,
+                    modifiers,
+                ) => {
+                    let stops = if modifiers.contains(glfw::Modifiers::Shift) {
+                        -ToneMapping::EXPOSURE_STEP
+                    } else {
+                        ToneMapping::EXPOSURE_STEP
+                    };
+                    drawing_ctx.tone_mapping_mut().adjust_exposure(stops);
+                    println!("{}", drawing_ctx.tone_mapping().status());
+                }
+                glfw::WindowEvent::Key(
+                    glfw::Key::G,
+                    _,
+                    glfw::Action::Press | glfw::Action::Repeat,
+                    modifiers,
+                ) => {
+                    let delta = if modifiers.contains(glfw::Modifiers::Shift) {
+                        -ToneMapping::GAMMA_STEP
+                    } else {
+                        ToneMapping::GAMMA_STEP
+                    };
+                    drawing_ctx.tone_mapping_mut().adjust_gamma(delta);
+                    println!("{}", drawing_ctx.tone_mapping().status());
+                }
+                glfw::WindowEvent::Key(glfw::Key::T, _, glfw::Action::Press, _) => {
+                    drawing_ctx.tone_mapping_mut().cycle_operator();
+                    println!("{}", drawing_ctx.tone_mapping().status());
+                }
+                glfw::WindowEvent::Key(glfw::Key::L, _, glfw::Action::Press, _) => {
+                    drawing_ctx.tone_mapping_mut().toggle_enabled();
+                    println!("{}", drawing_ctx.tone_mapping().status());
+                }
+                glfw::WindowEvent::Key(glfw::Key::Num0, _, glfw::Action::Press, _) => {
+                    drawing_ctx.tone_mapping_mut().reset();
+                    drawing_ctx.panorama_camera_mut().reset();
+                    println!("{}", drawing_ctx.tone_mapping().status());
+                }
+                glfw::WindowEvent::Key(glfw::Key::S, _, glfw::Action::Press, _) => {
+                    drawing_ctx.toggle_color_interpretation();
+                    println!(
+                        "Interpreting colors as {}",
+                        drawing_ctx.color_interpretation().name()
+                    );
+                }
+                glfw::WindowEvent::Key(glfw::Key::C, _, glfw::Action::Press, _) => {
+                    drawing_ctx.toggle_color_management();
+                    println!("{}", drawing_ctx.color_management_status());
+                }
+                glfw::WindowEvent::Key(glfw::Key::P, _, glfw::Action::Press, _) => {
+                    drawing_ctx.toggle_pbo_uploads();
+                    let status = if drawing_ctx.pbo_uploads() {
+                        "Enabled"
+                    } else {
+                        "Disabled"
+                    };
+                    println!("{} pixel buffer object uploads", status);
+                }
+                glfw::WindowEvent::Key(glfw::Key::V, _, glfw::Action::Press, _) => {
+                    drawing_ctx.toggle_view_mode();
+                    if drawing_ctx.view_mode() == ViewMode::Panorama {
+                        println!("Panorama view: {}", drawing_ctx.panorama_status());
+                    } else {
+                        println!("Flat view");
+                    }
+                }
+                glfw::WindowEvent::Key(glfw::Key::M, _, glfw::Action::Press, _) => {
+                    drawing_ctx.cycle_panorama_layout_override();
+                    println!("{}", drawing_ctx.panorama_status());
+                }
+                glfw::WindowEvent::Key(glfw::Key::A, _, glfw::Action::Press, _) => {
+                    drawing_ctx.cycle_slice_axis();
+                    if let Some(volume_view) = drawing_ctx.volume_view() {
+                        println!("{}", volume_view.status());
+                    }
+                }
+                glfw::WindowEvent::Key(
+                    key @ (glfw::Key::Up | glfw::Key::Down),
+                    _,
+                    glfw::Action::Press | glfw::Action::Repeat,
+                    _,
+                ) => {
+                    let delta = if key == glfw::Key::Up { 1 } else { -1 };
+                    if let Some(volume_view) = drawing_ctx.volume_view_mut() {
+                        volume_view.move_slice(delta);
+                        println!("{}", volume_view.status());
+                    }
+                }
+                glfw::WindowEvent::Key(glfw::Key::I, _, glfw::Action::Press, _) => {
+                    if let Some(volume_view) = drawing_ctx.volume_view_mut() {
+                        volume_view.toggle_maximum_intensity_projection();
+                        println!("{}", volume_view.status());
+                    }
+                }
+                glfw::WindowEvent::Key(glfw::Key::O, _, glfw::Action::Press, _) => {
+                    drawing_ctx.toggle_volume_montage();
+                    if let Some(volume_view) = drawing_ctx.volume_view() {
+                        println!("{}", volume_view.status());
+                    }
+                }
+                glfw::WindowEvent::Key(glfw::Key::B, _, glfw::Action::Press, _) => {
+                    println!("{}", drawing_ctx.memory_status());
+                }
+                glfw::WindowEvent::Key(glfw::Key::D, _, glfw::Action::Press, _) => {
+                    if !drawing_ctx.has_display_modes() {
+                        println!("No custom display modes loaded");
+                        continue;
+                    }
+                    drawing_ctx.cycle_display_mode();
+                    println!(
+                        "Display mode: {}",
+                        drawing_ctx.display_mode_name().unwrap_or("default")
+                    );
+                }
+                glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, action, _) => {
+                    drag_position = match action {
+                        glfw::Action::Press => {
+                            let (x, y) = window.get_cursor_pos();
+                            Some(Vector2::new(x as f32, y as f32))
+                        }
+                        _ => None,
+                    };
+                }
+                glfw::WindowEvent::MouseButton(
+                    glfw::MouseButton::Button2,
+                    glfw::Action::Press,
+                    _,
+                ) => {
+                    let (x, y) = window.get_cursor_pos();
+                    drawing_ctx.print_texel_at(Vector2::new(x as f32, y as f32));
+                }
+                glfw::WindowEvent::CursorPos(x, y) => {
+                    let Some(last_position) = drag_position else {
+                        continue;
+                    };
+
+                    let position = Vector2::new(x as f32, y as f32);
+                    if drawing_ctx.view_mode() == ViewMode::Panorama {
+                        let (_, height) = window.get_size();
+                        drawing_ctx
+                            .panorama_camera_mut()
+                            .rotate_by_drag(position - last_position, height);
+                    }
+                    drag_position = Some(position);
+                }
+                glfw::WindowEvent::Scroll(_, y_offset) => {
+                    let is_held = |key| window.get_key(key) == glfw::Action::Press;
+                    let control_held =
+                        is_held(glfw::Key::LeftControl) || is_held(glfw::Key::RightControl);
+                    let shift_held =
+                        is_held(glfw::Key::LeftShift) || is_held(glfw::Key::RightShift);
+
+                    if control_held {
+                        let stops = y_offset as f32 * ToneMapping::EXPOSURE_STEP;
+                        drawing_ctx.tone_mapping_mut().adjust_exposure(stops);
+                    } else if shift_held {
+                        let delta = y_offset as f32 * ToneMapping::GAMMA_STEP;
+                        drawing_ctx.tone_mapping_mut().adjust_gamma(delta);
+                    } else if let Some(volume_view) = drawing_ctx.volume_view_mut() {
+                        volume_view.move_slice(y_offset.round() as i32);
+                    } else if drawing_ctx.view_mode() == ViewMode::Panorama {
+                        drawing_ctx.panorama_camera_mut().zoom(y_offset as f32);
+                        println!(
+                            "Field of view: {:.0}°",
+                            drawing_ctx.panorama_camera().field_of_view()
+                        );
+                    }
+                }
                 glfw::WindowEvent::FileDrop(paths) => {
                     for path in paths.iter() {
                         let successfully_loaded = drawing_ctx.handle_file_path(path);
@@ -123,6 +373,29 @@ fn main() {
             "Disabled"
         };
 
-        window.set_title(&format!("imdrip - Resize on load? {}", resize_on_load_status));
+        let view_status = match (drawing_ctx.volume_view(), drawing_ctx.view_mode()) {
+            (Some(volume_view), _) => format!(" - {}", volume_view.status()),
+            (None, ViewMode::Flat) => drawing_ctx
+                .display_mode_name()
+                .map(|name| format!(" - {}", name))
+                .unwrap_or_default(),
+            (None, ViewMode::Panorama) => format!(" - {}", drawing_ctx.panorama_layout().name()),
+        };
+
+        let format_status = drawing_ctx
+            .texel_format()
+            .map(|format| format!(" - {}", format.name()))
+            .unwrap_or_default();
+
+        window.set_title(&format!(
+            "imdrip - Resize on load? {}{}{} - {} - {} - {} - GPU {}",
+            resize_on_load_status,
+            view_status,
+            format_status,
+            drawing_ctx.color_interpretation().name(),
+            drawing_ctx.color_management_status(),
+            drawing_ctx.tone_mapping().status(),
+            resource_tracker::format_bytes(resource_tracker::total_bytes())
+        ));
     }
 }
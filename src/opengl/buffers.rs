@@ -1,7 +1,11 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use super::resource_tracker::{self, ResourceKind};
+use super::state_cache;
+
 pub fn unbind(target: gl::types::GLenum) {
-    unsafe {
-        gl::BindBuffer(target, 0);
-    }
+    state_cache::bind_buffer(target, 0);
 }
 
 
@@ -34,7 +38,329 @@ pub fn create_buffer() -> u32 {
 }
 
 pub fn delete_buffer(handle: u32) {
+    state_cache::forget_buffer(handle);
     unsafe {
         gl::DeleteBuffers(1, &handle);
     }
 }
+
+pub trait BufferTarget {
+    const TARGET: gl::types::GLenum;
+    const RESOURCE_KIND: ResourceKind;
+}
+
+macro_rules! buffer_target {
+    ($name:ident, $target:expr, $resource_kind:expr) => {
+        pub struct $name;
+
+        impl BufferTarget for $name {
+            const TARGET: gl::types::GLenum = $target;
+            const RESOURCE_KIND: ResourceKind = $resource_kind;
+        }
+    };
+}
+
+buffer_target!(ArrayTarget, gl::ARRAY_BUFFER, ResourceKind::VertexBuffer);
+buffer_target!(ElementArrayTarget, gl::ELEMENT_ARRAY_BUFFER, ResourceKind::ElementBuffer);
+buffer_target!(UniformTarget, gl::UNIFORM_BUFFER, ResourceKind::UniformBuffer);
+buffer_target!(PixelPackTarget, gl::PIXEL_PACK_BUFFER, ResourceKind::PixelBuffer);
+buffer_target!(PixelUnpackTarget, gl::PIXEL_UNPACK_BUFFER, ResourceKind::PixelBuffer);
+buffer_target!(CopyReadTarget, gl::COPY_READ_BUFFER, ResourceKind::CopyBuffer);
+buffer_target!(CopyWriteTarget, gl::COPY_WRITE_BUFFER, ResourceKind::CopyBuffer);
+
+// Buffer object bound to the target of T. Data is copied into the bound
+// buffer, so copy_data, sub_data and orphan expect it to be bound.
+pub struct Buffer<T: BufferTarget> {
+    handle: u32,
+    size: usize,
+    target: PhantomData<T>,
+}
+
+impl<T: BufferTarget> Buffer<T> {
+    pub fn new() -> Self {
+        let handle = create_buffer();
+        resource_tracker::register(T::RESOURCE_KIND, handle);
+        Self {
+            handle,
+            size: 0,
+            target: PhantomData,
+        }
+    }
+
+    pub fn bind(&self) {
+        self.bind_to(T::TARGET);
+    }
+
+    fn bind_to(&self, target: gl::types::GLenum) {
+        state_cache::bind_buffer(target, self.handle);
+    }
+
+    pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
+    where
+        SetupFn: FnMut(&mut Self),
+    {
+        self.bind();
+        setup(&mut self);
+        unbind(T::TARGET);
+        self
+    }
+
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    // In bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Respecifying the whole buffer orphans the previous storage, so this
+    // doesn't stall while an earlier draw or upload still reads from it
+    pub fn copy_data<D>(&mut self, data: &[D], mode: gl::types::GLenum) {
+        copy_data_to_bound_target(T::TARGET, data, mode);
+        self.set_size(std::mem::size_of_val(data));
+    }
+
+    pub fn copy_data_static<D>(&mut self, data: &[D]) {
+        self.copy_data(data, gl::STATIC_DRAW);
+    }
+
+    // Allocates uninitialized storage, e.g. to fill it with sub_data later
+    pub fn allocate(&mut self, size: usize, mode: gl::types::GLenum) {
+        unsafe {
+            gl::BufferData(T::TARGET, size as isize, std::ptr::null(), mode);
+        }
+        self.set_size(size);
+    }
+
+    // Replaces the storage with new storage of the same size, so that
+    // streamed data can be written without waiting for the GPU
+    pub fn orphan(&mut self, mode: gl::types::GLenum) {
+        self.allocate(self.size, mode);
+    }
+
+    // The offset is in bytes
+    pub fn sub_data<D>(&mut self, offset: usize, data: &[D]) {
+        let bytes = std::mem::size_of_val(data);
+        assert!(
+            offset + bytes <= self.size,
+            "Range {}..{} is outside of buffer {} ({} bytes)",
+            offset,
+            offset + bytes,
+            self.handle,
+            self.size
+        );
+
+        unsafe {
+            gl::BufferSubData(
+                T::TARGET,
+                offset as isize,
+                bytes as isize,
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+        }
+    }
+
+    // Binds the buffer and maps the byte range with the access of A. The
+    // flags can add GL_MAP_INVALIDATE_*, GL_MAP_UNSYNCHRONIZED_BIT and
+    // GL_MAP_FLUSH_EXPLICIT_BIT, the first two only without read access.
+    pub fn map_range<A: MapAccess>(
+        &mut self,
+        offset: usize,
+        length: usize,
+        flags: gl::types::GLbitfield,
+    ) -> Result<BufferMapping<'_, T, A>, String> {
+        if offset + length > self.size {
+            return Err(format!(
+                "Range {}..{} is outside of buffer {} ({} bytes)",
+                offset,
+                offset + length,
+                self.handle,
+                self.size
+            ));
+        }
+
+        let access_flags = gl::MAP_READ_BIT | gl::MAP_WRITE_BIT;
+        let write_only_flags = gl::MAP_INVALIDATE_RANGE_BIT
+            | gl::MAP_INVALIDATE_BUFFER_BIT
+            | gl::MAP_UNSYNCHRONIZED_BIT;
+        if flags & access_flags != 0
+            || (A::ACCESS & gl::MAP_READ_BIT != 0 && flags & write_only_flags != 0)
+        {
+            return Err(format!(
+                "Invalid flags {:#x} for mapping buffer {}",
+                flags, self.handle
+            ));
+        }
+
+        self.bind();
+        let data = unsafe {
+            gl::MapBufferRange(
+                T::TARGET,
+                offset as isize,
+                length as isize,
+                A::ACCESS | flags,
+            ) as *mut u8
+        };
+        if data.is_null() {
+            return Err(format!("Failed to map buffer {}", self.handle));
+        }
+
+        Ok(BufferMapping {
+            buffer: self,
+            data,
+            length,
+            access: PhantomData,
+        })
+    }
+
+    // Copies on the GPU, without changing the bindings of either target
+    pub fn copy_from<S: BufferTarget>(
+        &mut self,
+        source: &Buffer<S>,
+        read_offset: usize,
+        write_offset: usize,
+        size: usize,
+    ) {
+        assert!(
+            read_offset + size <= source.size && write_offset + size <= self.size,
+            "Copy of {} bytes from buffer {} to buffer {} is out of range",
+            size,
+            source.handle,
+            self.handle
+        );
+
+        source.bind_to(gl::COPY_READ_BUFFER);
+        self.bind_to(gl::COPY_WRITE_BUFFER);
+        unsafe {
+            gl::CopyBufferSubData(
+                gl::COPY_READ_BUFFER,
+                gl::COPY_WRITE_BUFFER,
+                read_offset as isize,
+                write_offset as isize,
+                size as isize,
+            );
+        }
+        unbind(gl::COPY_READ_BUFFER);
+        unbind(gl::COPY_WRITE_BUFFER);
+    }
+
+    fn set_size(&mut self, size: usize) {
+        self.size = size;
+        resource_tracker::set_size(T::RESOURCE_KIND, self.handle, T::TARGET, size);
+    }
+}
+
+impl<T: BufferTarget> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        resource_tracker::unregister(T::RESOURCE_KIND, self.handle);
+        delete_buffer(self.handle);
+    }
+}
+
+// Access of a mapping. Mapped memory can only be read through ReadOnly and
+// ReadWrite mappings, since write-only mappings (especially invalidated
+// ones) have undefined contents.
+pub trait MapAccess {
+    const ACCESS: gl::types::GLbitfield;
+}
+
+pub trait ReadableAccess: MapAccess {}
+pub trait WritableAccess: MapAccess {}
+
+pub struct ReadOnly;
+pub struct WriteOnly;
+pub struct ReadWrite;
+
+impl MapAccess for ReadOnly {
+    const ACCESS: gl::types::GLbitfield = gl::MAP_READ_BIT;
+}
+
+impl MapAccess for WriteOnly {
+    const ACCESS: gl::types::GLbitfield = gl::MAP_WRITE_BIT;
+}
+
+impl MapAccess for ReadWrite {
+    const ACCESS: gl::types::GLbitfield = gl::MAP_READ_BIT | gl::MAP_WRITE_BIT;
+}
+
+impl ReadableAccess for ReadOnly {}
+impl ReadableAccess for ReadWrite {}
+impl WritableAccess for WriteOnly {}
+impl WritableAccess for ReadWrite {}
+
+// Mapped range of a buffer. Dropping it unmaps the buffer as well, but only
+// unmap reports whether the contents were lost in the meantime.
+pub struct BufferMapping<'a, T: BufferTarget, A: MapAccess> {
+    buffer: &'a mut Buffer<T>,
+    // Null once unmapped
+    data: *mut u8,
+    length: usize,
+    access: PhantomData<A>,
+}
+
+impl<T: BufferTarget, A: MapAccess> BufferMapping<'_, T, A> {
+    // Fails if the contents were lost while the buffer was mapped (e.g. when
+    // the screen mode changed), then they have to be written again
+    pub fn unmap(mut self) -> Result<(), String> {
+        if self.unmap_buffer() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Contents of buffer {} were lost while it was mapped",
+                self.buffer.handle
+            ))
+        }
+    }
+
+    fn unmap_buffer(&mut self) -> bool {
+        self.data = std::ptr::null_mut();
+        // Something else might have been bound to the target in the meantime
+        self.buffer.bind();
+        unsafe { gl::UnmapBuffer(T::TARGET) == gl::TRUE }
+    }
+}
+
+impl<T: BufferTarget, A: WritableAccess> BufferMapping<'_, T, A> {
+    // The offset is in bytes, from the start of the mapped range
+    pub fn write<D: Copy>(&mut self, offset: usize, data: &[D]) {
+        let bytes = std::mem::size_of_val(data);
+        assert!(
+            offset + bytes <= self.length,
+            "Write of {} bytes at {} is outside of the mapped range ({} bytes)",
+            bytes,
+            offset,
+            self.length
+        );
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr() as *const u8,
+                self.data.add(offset),
+                bytes,
+            );
+        }
+    }
+}
+
+impl<T: BufferTarget, A: ReadableAccess> Deref for BufferMapping<'_, T, A> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.data, self.length) }
+    }
+}
+
+impl<T: BufferTarget> DerefMut for BufferMapping<'_, T, ReadWrite> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.data, self.length) }
+    }
+}
+
+impl<T: BufferTarget, A: MapAccess> Drop for BufferMapping<'_, T, A> {
+    fn drop(&mut self) {
+        if !self.data.is_null() {
+            self.unmap_buffer();
+        }
+    }
+}
@@ -0,0 +1,61 @@
+pub fn version() -> (i32, i32) {
+    unsafe {
+        let mut major = 0;
+        let mut minor = 0;
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        (major, minor)
+    }
+}
+
+pub fn is_version_at_least(major: i32, minor: i32) -> bool {
+    version() >= (major, minor)
+}
+
+pub fn has_extension(name: &str) -> bool {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+
+        (0..count as u32).any(|index| {
+            let extension = gl::GetStringi(gl::EXTENSIONS, index);
+            !extension.is_null()
+                && std::ffi::CStr::from_ptr(extension as *const i8).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+pub fn get_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let value = gl::GetString(name);
+        if value.is_null() {
+            return String::new();
+        }
+
+        std::ffi::CStr::from_ptr(value as *const i8)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+// OpenGL ES lacks some desktop functionality, e.g. glGetTexImage
+pub fn is_gles() -> bool {
+    get_string(gl::VERSION).starts_with("OpenGL ES")
+}
+
+pub fn default_framebuffer_is_srgb() -> bool {
+    unsafe {
+        let mut encoding = 0;
+        gl::GetFramebufferAttachmentParameteriv(
+            gl::FRAMEBUFFER,
+            gl::BACK_LEFT,
+            gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+            &mut encoding,
+        );
+        encoding as gl::types::GLenum == gl::SRGB
+    }
+}
+
+pub fn set_framebuffer_srgb_enabled(enabled: bool) {
+    super::state_cache::set_capability(gl::FRAMEBUFFER_SRGB, enabled);
+}
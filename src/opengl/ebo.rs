@@ -1,43 +1,20 @@
-use super::buffers;
+use super::buffers::{Buffer, ElementArrayTarget};
 
-pub struct Ebo {
-    handle: u32,
+// Smaller index types save memory for meshes with few vertices
+pub trait IndexType: Copy {
+    const GL_TYPE: gl::types::GLenum;
 }
 
-impl Ebo {
-    pub fn new() -> Self {
-        Self {
-            handle: buffers::create_buffer()
-        }
-    }
-
-    pub fn bind(&self) {
-        unsafe {
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.handle);
-        }
-    }
-
-    pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
-    where
-        SetupFn: FnMut(&mut Self)
-    {
-        self.bind();
-        setup(&mut self);
-        buffers::unbind(gl::ELEMENT_ARRAY_BUFFER);
-        self
-    }
+impl IndexType for u8 {
+    const GL_TYPE: gl::types::GLenum = gl::UNSIGNED_BYTE;
+}
 
-    pub fn copy_data(&mut self, data: &[u32], mode: gl::types::GLenum) {
-        buffers::copy_data_to_bound_target(gl::ELEMENT_ARRAY_BUFFER, data, mode);
-    }
-    
-    pub fn copy_data_static(&mut self, data: &[u32]) {
-        buffers::copy_data_to_bound_target_static(gl::ELEMENT_ARRAY_BUFFER, data);
-    }
+impl IndexType for u16 {
+    const GL_TYPE: gl::types::GLenum = gl::UNSIGNED_SHORT;
 }
 
-impl Drop for Ebo {  
-    fn drop(&mut self) {
-        buffers::delete_buffer(self.handle);
-    }
+impl IndexType for u32 {
+    const GL_TYPE: gl::types::GLenum = gl::UNSIGNED_INT;
 }
+
+pub type Ebo = Buffer<ElementArrayTarget>;
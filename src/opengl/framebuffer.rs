@@ -0,0 +1,94 @@
+pub fn unbind() {
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+}
+
+pub struct Framebuffer {
+    handle: u32,
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        unsafe {
+            let mut handle = 0;
+            gl::GenFramebuffers(1, &mut handle);
+            Self { handle }
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.handle);
+        }
+    }
+
+    pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
+    where
+        SetupFn: FnMut(&mut Self),
+    {
+        self.bind();
+        setup(&mut self);
+        unbind();
+        self
+    }
+
+    // Has to be bound
+    pub fn attach_texture_2d(&mut self, attachment: gl::types::GLenum, texture: u32, level: i32) {
+        unsafe {
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, gl::TEXTURE_2D, texture, level);
+        }
+    }
+
+    // Has to be bound
+    pub fn check_status(&self) -> Result<(), String> {
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        match status {
+            gl::FRAMEBUFFER_COMPLETE => Ok(()),
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => {
+                Err(String::from("Framebuffer attachment is incomplete"))
+            }
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
+                Err(String::from("Framebuffer has no attachments"))
+            }
+            gl::FRAMEBUFFER_UNSUPPORTED => {
+                Err(String::from("Framebuffer attachment formats are unsupported"))
+            }
+            _ => Err(format!("Framebuffer is incomplete (status {:#x})", status)),
+        }
+    }
+
+    // Reads from the first color attachment, the framebuffer has to be bound
+    pub unsafe fn read_pixels_into_raw_ptr(
+        &self,
+        size: (i32, i32),
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+        data_ptr: *mut std::ffi::c_void,
+    ) {
+        unsafe {
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::ReadPixels(
+                0,
+                0,
+                size.0,
+                size.1,
+                source_format,
+                source_data_type,
+                data_ptr,
+            );
+        }
+    }
+
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.handle);
+        }
+    }
+}
@@ -2,28 +2,65 @@ use std::rc::Rc;
 
 use crate::opengl::shader::shader_program::ShaderProgram;
 
+use super::parameters::MaterialParameters;
+use super::render_state::RenderState;
 use super::Material;
 
 pub struct BasicMaterial {
     shader_program: Rc<ShaderProgram>,
+    parameters: MaterialParameters,
+    render_state: RenderState,
 }
 
 impl BasicMaterial {
     pub fn new(shader_program: Rc<ShaderProgram>) -> Self {
-        Self { shader_program }
+        Self::with_parameters(shader_program, MaterialParameters::new())
+    }
+
+    pub fn with_parameters(
+        shader_program: Rc<ShaderProgram>,
+        parameters: MaterialParameters,
+    ) -> Self {
+        Self {
+            shader_program,
+            parameters,
+            render_state: RenderState::default(),
+        }
+    }
+
+    pub fn parameters_mut(&mut self) -> &mut MaterialParameters {
+        &mut self.parameters
+    }
+
+    pub fn render_state(&self) -> RenderState {
+        self.render_state
+    }
+
+    pub fn set_render_state(&mut self, render_state: RenderState) {
+        self.render_state = render_state;
     }
 }
 
 impl Material for BasicMaterial {
     fn bind(&self) {
         self.shader_program.bind();
-    }
-
-    fn unbind(&self) {
-        crate::opengl::shader::shader_program::unbind();
+        self.shader_program.set_uniforms(&self.parameters);
+        self.render_state.apply();
     }
 
     fn shader_program(&self) -> &ShaderProgram {
         &self.shader_program
     }
+
+    fn parameters(&self) -> &MaterialParameters {
+        &self.parameters
+    }
+
+    fn clone_with_parameters(&self, overrides: &MaterialParameters) -> Rc<dyn Material> {
+        Rc::new(Self {
+            shader_program: Rc::clone(&self.shader_program),
+            parameters: self.parameters.merged_with(overrides),
+            render_state: self.render_state,
+        })
+    }
 }
@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use nalgebra::{Vector2, Vector3, Vector4};
+use serde::Deserialize;
+
+use crate::opengl::shader::binary_cache::ProgramBinaryCache;
+use crate::opengl::shader::builder::{ShaderProgramBuilder, ShaderStage};
+use crate::opengl::shader::preprocessor::{PreprocessedSource, ShaderPreprocessor};
+use crate::opengl::shader::registry::ShaderRegistry;
+use crate::opengl::shader::shader_program::ShaderProgram;
+use crate::opengl::texture::loading;
+
+use super::parameters::MaterialParameters;
+use super::render_state::RenderState;
+use super::textured::{TextureKind, TexturedMaterial};
+
+// Shaders and materials declared in a TOML file, e.g.
+//
+//   [shaders.false_color]
+//   vertex = "quad.vert"
+//   fragment = "false_color.frag"
+//   defines = { STEPS = 8 }
+//
+//   [materials.false_color]
+//   shader = "false_color"
+//   textures = { palette_texture = "palette.png" }
+//   parameters = { contrast = 1.5, low_color = [0.0, 0.0, 0.5] }
+//   render_state = { blend = true }
+//
+// Stage sources and texture paths are relative to the definition file,
+// stage sources that aren't files there are looked up as embedded sources.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefinitionFile {
+    #[serde(default)]
+    shaders: BTreeMap<String, ShaderDefinition>,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShaderDefinition {
+    vertex: String,
+    fragment: String,
+    geometry: Option<String>,
+    #[serde(default)]
+    defines: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDefinition {
+    shader: String,
+    #[serde(default)]
+    textures: BTreeMap<String, PathBuf>,
+    #[serde(default)]
+    parameters: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    render_state: RenderStateDefinition,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderStateDefinition {
+    #[serde(default)]
+    blend: bool,
+    #[serde(default)]
+    depth_test: bool,
+    #[serde(default)]
+    cull_face: bool,
+}
+
+impl From<RenderStateDefinition> for RenderState {
+    fn from(definition: RenderStateDefinition) -> Self {
+        Self {
+            blend: definition.blend,
+            depth_test: definition.depth_test,
+            cull_face: definition.cull_face,
+        }
+    }
+}
+
+// Shaders and materials in the order of their names
+pub struct Definitions {
+    pub shaders: Vec<(String, Rc<ShaderProgram>)>,
+    pub materials: Vec<(String, TexturedMaterial)>,
+    // Problems that didn't stop loading, e.g. a failing program binary cache
+    pub warnings: Vec<String>,
+}
+
+// Materials can use the shaders of the same file or ones already in
+// `shader_registry`. Errors are prefixed with the file and the failing key.
+pub fn load_definitions<P: AsRef<Path>>(
+    path: P,
+    preprocessor: &ShaderPreprocessor,
+    shader_registry: &ShaderRegistry,
+) -> Result<Definitions, String> {
+    let path = path.as_ref();
+    let file_name = path.to_string_lossy();
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("{}: Failed to read definitions: {}", file_name, err))?;
+    let file: DefinitionFile =
+        toml::from_str(&text).map_err(|err| format!("{}: {}", file_name, err))?;
+
+    let mut shaders = Vec::new();
+    let mut warnings = Vec::new();
+    for (name, definition) in file.shaders {
+        let key = format!("shaders.{}", name);
+        let (shader_program, cache_errors) =
+            build_shader(&definition, preprocessor, base_dir, &key)
+                .map_err(|err| format!("{}: {}", file_name, err))?;
+        warnings.extend(
+            cache_errors
+                .into_iter()
+                .map(|err| format!("{}: {}: {}", file_name, key, err)),
+        );
+        shaders.push((name, Rc::new(shader_program)));
+    }
+
+    let mut materials = Vec::new();
+    for (name, definition) in file.materials {
+        let key = format!("materials.{}", name);
+        let shader_program = shaders
+            .iter()
+            .find(|(shader_name, _)| *shader_name == definition.shader)
+            .map(|(_, shader_program)| Rc::clone(shader_program))
+            .or_else(|| shader_registry.get_clone_ref(&definition.shader))
+            .ok_or_else(|| {
+                format!(
+                    "{}: {}.shader: No shader with name \"{}\" exists",
+                    file_name, key, definition.shader
+                )
+            })?;
+        let material = build_material(definition, shader_program, base_dir, &key)
+            .map_err(|err| format!("{}: {}", file_name, err))?;
+        materials.push((name, material));
+    }
+
+    Ok(Definitions {
+        shaders,
+        materials,
+        warnings,
+    })
+}
+
+// The program is built from source if the binary cache fails, its errors are
+// returned next to the program
+fn build_shader(
+    definition: &ShaderDefinition,
+    preprocessor: &ShaderPreprocessor,
+    base_dir: &Path,
+    key: &str,
+) -> Result<(ShaderProgram, Vec<String>), String> {
+    let mut preprocessor = preprocessor.clone();
+    for (name, value) in definition.defines.iter() {
+        let value = define_value(value)
+            .ok_or_else(|| format!("{}.defines.{}: Unsupported value {}", key, name, value))?;
+        preprocessor.define(name, &value);
+    }
+
+    let mut stages = vec![
+        (ShaderStage::Vertex, &definition.vertex),
+        (ShaderStage::Fragment, &definition.fragment),
+    ];
+    if let Some(geometry) = &definition.geometry {
+        stages.push((ShaderStage::Geometry, geometry));
+    }
+
+    let mut builder = ShaderProgramBuilder::new();
+    for (stage, source) in stages {
+        let preprocessed = preprocess_stage(&preprocessor, base_dir, source)
+            .map_err(|err| format!("{}.{}: {}", key, stage.name(), err))?;
+        builder = builder.add_preprocessed_source(stage, preprocessed);
+    }
+
+    builder
+        .use_binary_cache(ProgramBinaryCache::from_xdg_cache_dir())
+        .build_with_cache_errors()
+        .map_err(|err| format!("{}: {}", key, err))
+}
+
+fn preprocess_stage(
+    preprocessor: &ShaderPreprocessor,
+    base_dir: &Path,
+    source: &str,
+) -> Result<PreprocessedSource, String> {
+    let path = base_dir.join(source);
+    if path.is_file() {
+        preprocessor.process_file(path)
+    } else {
+        preprocessor.process_embedded(source).map_err(|_| {
+            format!(
+                "\"{}\" is neither a file nor an embedded shader source",
+                source
+            )
+        })
+    }
+}
+
+fn define_value(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(format!("{:?}", value)),
+        toml::Value::Boolean(value) => Some(String::from(if *value { "1" } else { "0" })),
+        _ => None,
+    }
+}
+
+fn build_material(
+    definition: MaterialDefinition,
+    shader_program: Rc<ShaderProgram>,
+    base_dir: &Path,
+    key: &str,
+) -> Result<TexturedMaterial, String> {
+    let parameters = material_parameters(&definition, key)?;
+    let mut material = TexturedMaterial::with_parameters(shader_program, vec![], parameters);
+    material.set_render_state(definition.render_state.into());
+
+    for (sampler_name, path) in definition.textures.iter() {
+        let image = loading::open_texture_image(base_dir.join(path))
+            .map_err(|err| format!("{}.textures.{}: {}", key, sampler_name, err))?;
+        let format = image.format();
+        let (texture, _) = loading::create_from_image(image);
+        material
+            .set_texture(sampler_name, TextureKind::TwoDimensional { texture, format })
+            .map_err(|err| format!("{}.textures.{}: {}", key, sampler_name, err))?;
+    }
+
+    Ok(material)
+}
+
+fn material_parameters(
+    definition: &MaterialDefinition,
+    key: &str,
+) -> Result<MaterialParameters, String> {
+    let mut parameters = MaterialParameters::new();
+    for (name, value) in definition.parameters.iter() {
+        set_parameter(&mut parameters, name, value)
+            .map_err(|err| format!("{}.parameters.{}: {}", key, name, err))?;
+    }
+    Ok(parameters)
+}
+
+// Integers are set as int, floats as float and arrays of 2 to 4 numbers as
+// vectors (ivec if all of them are integers)
+fn set_parameter(
+    parameters: &mut MaterialParameters,
+    name: &str,
+    value: &toml::Value,
+) -> Result<(), String> {
+    match value {
+        toml::Value::Boolean(value) => parameters.set(name, *value),
+        toml::Value::Integer(value) => parameters.set(name, int_value(*value)?),
+        toml::Value::Float(value) => parameters.set(name, *value as f32),
+        toml::Value::Array(values) if values.iter().all(|value| value.is_integer()) => {
+            let values = values
+                .iter()
+                .map(|value| int_value(value.as_integer().unwrap()))
+                .collect::<Result<Vec<i32>, String>>()?;
+            match values.as_slice() {
+                [x, y] => parameters.set(name, Vector2::new(*x, *y)),
+                [x, y, z] => parameters.set(name, Vector3::new(*x, *y, *z)),
+                [x, y, z, w] => parameters.set(name, Vector4::new(*x, *y, *z, *w)),
+                _ => return Err(vector_length_error(values.len())),
+            }
+        }
+        toml::Value::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| match value {
+                    toml::Value::Float(value) => Ok(*value as f32),
+                    toml::Value::Integer(value) => Ok(*value as f32),
+                    _ => Err(format!("Vector component {} is not a number", value)),
+                })
+                .collect::<Result<Vec<f32>, String>>()?;
+            match values.as_slice() {
+                [x, y] => parameters.set(name, Vector2::new(*x, *y)),
+                [x, y, z] => parameters.set(name, Vector3::new(*x, *y, *z)),
+                [x, y, z, w] => parameters.set(name, Vector4::new(*x, *y, *z, *w)),
+                _ => return Err(vector_length_error(values.len())),
+            }
+        }
+        _ => return Err(format!("Unsupported value {}", value)),
+    }
+    Ok(())
+}
+
+fn int_value(value: i64) -> Result<i32, String> {
+    i32::try_from(value).map_err(|_| format!("{} doesn't fit into an int", value))
+}
+
+fn vector_length_error(length: usize) -> String {
+    format!("Vectors need 2 to 4 components, got {}", length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> DefinitionFile {
+        toml::from_str(text).unwrap()
+    }
+
+    fn glsl_type(parameters: &MaterialParameters, name: &str) -> &'static str {
+        parameters.get(name).unwrap().glsl_type_name()
+    }
+
+    #[test]
+    fn parameters_are_typed_by_their_values() {
+        let file = parse(
+            r#"
+            [materials.false_color]
+            shader = "image"
+            parameters = { enabled = true, steps = 8, contrast = 1.5, offset = [1, 2], low_color = [0.0, 0, 0.5], tint = [1.0, 1.0, 1.0, 0.5] }
+            "#,
+        );
+
+        let parameters =
+            material_parameters(&file.materials["false_color"], "materials.false_color").unwrap();
+        assert_eq!(glsl_type(&parameters, "enabled"), "bool");
+        assert_eq!(glsl_type(&parameters, "steps"), "int");
+        assert_eq!(glsl_type(&parameters, "contrast"), "float");
+        assert_eq!(glsl_type(&parameters, "offset"), "ivec2");
+        assert_eq!(glsl_type(&parameters, "low_color"), "vec3");
+        assert_eq!(glsl_type(&parameters, "tint"), "vec4");
+    }
+
+    #[test]
+    fn parameter_errors_name_the_failing_key() {
+        let file = parse(
+            r#"
+            [materials.x]
+            shader = "image"
+            parameters = { y = [1.0, 2.0, 3.0, 4.0, 5.0] }
+            "#,
+        );
+
+        let err = material_parameters(&file.materials["x"], "materials.x")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            "materials.x.parameters.y: Vectors need 2 to 4 components, got 5"
+        );
+    }
+
+    #[test]
+    fn unsupported_parameter_values_are_rejected() {
+        let mut parameters = MaterialParameters::new();
+        assert!(set_parameter(&mut parameters, "name", &toml::Value::from("text")).is_err());
+        assert!(set_parameter(&mut parameters, "big", &toml::Value::from(1i64 << 40)).is_err());
+        assert!(set_parameter(
+            &mut parameters,
+            "mixed",
+            &toml::Value::from(vec![toml::Value::from(1.0), toml::Value::from("a")])
+        )
+        .is_err());
+        assert!(parameters.is_empty());
+    }
+
+    #[test]
+    fn define_values_are_written_as_glsl() {
+        let file = parse(
+            r#"
+            [shaders.false_color]
+            vertex = "quad.vert"
+            fragment = "false_color.frag"
+            defines = { STEPS = 8, SCALE = 2.0, MODE = "LINEAR", SMOOTH = true, LIST = [1] }
+            "#,
+        );
+
+        let defines = &file.shaders["false_color"].defines;
+        assert_eq!(define_value(&defines["STEPS"]).as_deref(), Some("8"));
+        assert_eq!(define_value(&defines["SCALE"]).as_deref(), Some("2.0"));
+        assert_eq!(define_value(&defines["MODE"]).as_deref(), Some("LINEAR"));
+        assert_eq!(define_value(&defines["SMOOTH"]).as_deref(), Some("1"));
+        assert_eq!(define_value(&defines["LIST"]), None);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let result = toml::from_str::<DefinitionFile>(
+            r#"
+            [materials.x]
+            shader = "image"
+            colour = [1.0, 0.0, 0.0]
+            "#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn missing_shaders_name_the_material() {
+        let path = std::env::temp_dir().join(format!(
+            "imdrip_definitions_test_{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "[materials.x]\nshader = \"missing\"\n").unwrap();
+
+        let result = load_definitions(&path, &ShaderPreprocessor::new(), &ShaderRegistry::new());
+        std::fs::remove_file(&path).unwrap();
+
+        let err = result.err().unwrap();
+        assert!(
+            err.ends_with("materials.x.shader: No shader with name \"missing\" exists"),
+            "{}",
+            err
+        );
+    }
+}
@@ -1,14 +1,28 @@
+use std::rc::Rc;
+
 use crate::opengl::shader::shader_program::ShaderProgram;
 
+use parameters::MaterialParameters;
+
 pub mod basic;
+pub mod definitions;
+pub mod parameters;
 pub mod registry;
+pub mod render_state;
 pub mod textured;
 
+// Binding a material binds its shader program, sets its parameters and
+// applies its render state. Meshes don't unbind materials after drawing, so
+// every material sets the whole render state. Textures of earlier materials
+// may stay bound to other units, samplers only read the units they're set to.
 pub trait Material {
     fn bind(&self);
-    fn unbind(&self);
 
     fn shader_program(&self) -> &ShaderProgram;
+    fn parameters(&self) -> &MaterialParameters;
+
+    // The clone shares the shader program (and textures) of this material
+    fn clone_with_parameters(&self, overrides: &MaterialParameters) -> Rc<dyn Material>;
 }
 
 pub struct MockMaterial;
@@ -18,11 +32,15 @@ impl Material for MockMaterial {
         unimplemented!()
     }
 
-    fn unbind(&self) {
-        unimplemented!()
-    }
-
     fn shader_program(&self) -> &ShaderProgram {
         panic!("Mock material doesn't store shader")
     }
+
+    fn parameters(&self) -> &MaterialParameters {
+        panic!("Mock material doesn't store parameters")
+    }
+
+    fn clone_with_parameters(&self, _overrides: &MaterialParameters) -> Rc<dyn Material> {
+        Rc::new(MockMaterial)
+    }
 }
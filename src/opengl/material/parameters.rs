@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::opengl::shader::uniform_value::{UniformSet, UniformValue};
+
+// Uniform values a material sets whenever it's bound. Values are shared
+// between clones, so copying parameters for a variant is cheap.
+#[derive(Clone, Default)]
+pub struct MaterialParameters {
+    values: HashMap<String, Rc<dyn UniformValue>>,
+}
+
+impl MaterialParameters {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn with<V: UniformValue + 'static>(mut self, name: &str, value: V) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set<V: UniformValue + 'static>(&mut self, name: &str, value: V) {
+        self.values.insert(String::from(name), Rc::new(value));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn UniformValue> {
+        self.values.get(name).map(|value| value.as_ref())
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.values.remove(name).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(|name| name.as_str())
+    }
+
+    // Only the values for names that `other` has too
+    pub fn matching(&self, other: &MaterialParameters) -> Self {
+        let values = self
+            .values
+            .iter()
+            .filter(|(name, _)| other.has(name))
+            .map(|(name, value)| (name.clone(), Rc::clone(value)))
+            .collect();
+        Self { values }
+    }
+
+    // Values in `overrides` replace the ones with the same name
+    pub fn merged_with(&self, overrides: &MaterialParameters) -> Self {
+        let mut values = self.values.clone();
+        for (name, value) in overrides.values.iter() {
+            values.insert(name.clone(), Rc::clone(value));
+        }
+        Self { values }
+    }
+}
+
+impl UniformSet for MaterialParameters {
+    fn for_each_uniform(&self, apply: &mut dyn FnMut(&str, &dyn UniformValue)) {
+        for (name, value) in self.values.iter() {
+            apply(name, value.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_values_with_the_same_name() {
+        let parameters = MaterialParameters::new()
+            .with("exposure", 1.0f32)
+            .with("gamma", 2.2f32);
+        let overrides = MaterialParameters::new().with("exposure", 4.0f32);
+
+        let merged = parameters.merged_with(&overrides);
+        assert!(merged.has("gamma"));
+        assert!(std::ptr::addr_eq(
+            merged.get("exposure").unwrap(),
+            overrides.get("exposure").unwrap()
+        ));
+    }
+
+    #[test]
+    fn matching_keeps_own_values_for_shared_names() {
+        let parameters = MaterialParameters::new()
+            .with("exposure", 1.0f32)
+            .with("gamma", 2.2f32);
+        let overrides = MaterialParameters::new()
+            .with("exposure", 4.0f32)
+            .with("offset", 0.5f32);
+
+        let matching = parameters.matching(&overrides);
+        assert!(!matching.has("gamma"));
+        assert!(!matching.has("offset"));
+        assert!(std::ptr::addr_eq(
+            matching.get("exposure").unwrap(),
+            parameters.get("exposure").unwrap()
+        ));
+    }
+}
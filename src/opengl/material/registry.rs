@@ -3,9 +3,11 @@ use std::rc::Rc;
 
 use crate::opengl::mesh::Mesh;
 use crate::opengl::shader::shader_program::ShaderProgram;
+use crate::opengl::texture::texel_format::TexelFormat;
 use crate::opengl::texture::texture_2d::Texture2D;
 
 use super::basic::BasicMaterial;
+use super::parameters::MaterialParameters;
 use super::textured::TexturedMaterial;
 use super::Material;
 
@@ -33,9 +35,12 @@ impl MaterialRegistry {
         &mut self,
         name: &str,
         shader_program: Rc<ShaderProgram>,
+        sampler_name: &str,
         texture: Rc<Texture2D>,
+        format: TexelFormat,
     ) {
-        let material = TexturedMaterial::new_single_2d(shader_program, texture);
+        let material =
+            TexturedMaterial::new_single_2d(shader_program, sampler_name, texture, format);
         self.insert(name, Rc::new(material));
     }
 
@@ -47,6 +52,22 @@ impl MaterialRegistry {
         }
     }
 
+    // Registers a variant of an existing material, e.g. with another tint
+    pub fn insert_clone_with_parameters(
+        &mut self,
+        name: &str,
+        source_name: &str,
+        overrides: &MaterialParameters,
+    ) -> Result<(), String> {
+        let source = self
+            .material_map
+            .get(source_name)
+            .ok_or_else(|| format!("No material with name \"{}\" exists", source_name))?;
+        let material = source.clone_with_parameters(overrides);
+        self.insert(name, material);
+        Ok(())
+    }
+
     pub fn update_material_for(&self, name: &str, mesh: &mut Mesh) {
         if let Some(material) = self.get_clone_ref(name) {
             mesh.set_material(material);
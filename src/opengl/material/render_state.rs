@@ -0,0 +1,25 @@
+use crate::opengl::state_cache;
+
+// Fixed-function state a material enables while it's bound. Everything is
+// disabled by default, which matches the initial GL state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderState {
+    // Standard alpha blending (source alpha, one minus source alpha)
+    pub blend: bool,
+    pub depth_test: bool,
+    // Culls back faces
+    pub cull_face: bool,
+}
+
+impl RenderState {
+    pub fn apply(&self) {
+        state_cache::set_capability(gl::BLEND, self.blend);
+        if self.blend {
+            unsafe {
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            }
+        }
+        state_cache::set_capability(gl::DEPTH_TEST, self.depth_test);
+        state_cache::set_capability(gl::CULL_FACE, self.cull_face);
+    }
+}
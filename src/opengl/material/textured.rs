@@ -1,81 +1,205 @@
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use crate::opengl::shader::shader_program::ShaderProgram;
+use crate::opengl::texture::named_texture_bindings::NamedTextureBindings;
+use crate::opengl::texture::texel_format::TexelFormat;
 use crate::opengl::texture::texture_2d::Texture2D;
+use crate::opengl::texture::texture_2d_array::Texture2DArray;
+use crate::opengl::texture::texture_3d::Texture3D;
+use crate::opengl::texture::texture_cube_map::TextureCubeMap;
 
+use super::parameters::MaterialParameters;
+use super::render_state::RenderState;
 use super::Material;
 
+#[derive(Clone)]
 pub enum TextureKind {
-    TwoDimensional { texture: Rc<Texture2D> },
+    TwoDimensional {
+        texture: Rc<Texture2D>,
+        format: TexelFormat,
+    },
+    TwoDimensionalArray {
+        texture: Rc<Texture2DArray>,
+        format: TexelFormat,
+    },
+    CubeMap {
+        texture: Rc<TextureCubeMap>,
+        format: TexelFormat,
+    },
+    ThreeDimensional {
+        texture: Rc<Texture3D>,
+        format: TexelFormat,
+    },
 }
 
 impl TextureKind {
+    pub fn format(&self) -> TexelFormat {
+        match self {
+            TextureKind::TwoDimensional { format, .. }
+            | TextureKind::TwoDimensionalArray { format, .. }
+            | TextureKind::CubeMap { format, .. }
+            | TextureKind::ThreeDimensional { format, .. } => *format,
+        }
+    }
+
     pub fn bind(&self) {
         match self {
-            TextureKind::TwoDimensional { texture } => texture.bind(),
+            TextureKind::TwoDimensional { texture, .. } => texture.bind(),
+            TextureKind::TwoDimensionalArray { texture, .. } => texture.bind(),
+            TextureKind::CubeMap { texture, .. } => texture.bind(),
+            TextureKind::ThreeDimensional { texture, .. } => texture.bind(),
         }
     }
 
-    pub fn unbind(&self) {
+    pub fn bind_to_unit(&self, unit: u32) -> Result<(), String> {
         match self {
-            TextureKind::TwoDimensional { texture: _ } => {
-                crate::opengl::texture::texture_2d::unbind()
-            }
+            TextureKind::TwoDimensional { texture, .. } => texture.bind_to_unit(unit),
+            TextureKind::TwoDimensionalArray { texture, .. } => texture.bind_to_unit(unit),
+            TextureKind::CubeMap { texture, .. } => texture.bind_to_unit(unit),
+            TextureKind::ThreeDimensional { texture, .. } => texture.bind_to_unit(unit),
         }
     }
 }
 
+// Textures are keyed by the name of the sampler uniform they're bound to,
+// texture units are assigned when a texture is set
 pub struct TexturedMaterial {
     shader_program: Rc<ShaderProgram>,
-    textures: Vec<TextureKind>,
+    textures: HashMap<String, TextureKind>,
+    bindings: NamedTextureBindings,
+    parameters: MaterialParameters,
+    render_state: RenderState,
 }
 
 impl TexturedMaterial {
-    pub fn new(shader_program: Rc<ShaderProgram>, textures: Vec<TextureKind>) -> Self {
-        Self {
+    pub fn new(shader_program: Rc<ShaderProgram>, textures: Vec<(&str, TextureKind)>) -> Self {
+        Self::with_parameters(shader_program, textures, MaterialParameters::new())
+    }
+
+    pub fn with_parameters(
+        shader_program: Rc<ShaderProgram>,
+        textures: Vec<(&str, TextureKind)>,
+        parameters: MaterialParameters,
+    ) -> Self {
+        let mut material = Self {
             shader_program,
-            textures,
+            textures: HashMap::new(),
+            bindings: NamedTextureBindings::new(),
+            parameters,
+            render_state: RenderState::default(),
+        };
+
+        for (sampler_name, texture) in textures {
+            material
+                .set_texture(sampler_name, texture)
+                .unwrap_or_else(|err| panic!("{}", err));
         }
+        material
     }
 
-    pub fn new_single_2d(shader_program: Rc<ShaderProgram>, texture: Rc<Texture2D>) -> Self {
+    pub fn new_single_2d(
+        shader_program: Rc<ShaderProgram>,
+        sampler_name: &str,
+        texture: Rc<Texture2D>,
+        format: TexelFormat,
+    ) -> Self {
         Self::new(
             shader_program,
-            vec![TextureKind::TwoDimensional { texture }],
+            vec![(
+                sampler_name,
+                TextureKind::TwoDimensional { texture, format },
+            )],
         )
     }
 
-    pub fn textures(&self) -> &Vec<TextureKind> {
-        &self.textures
+    // Replaces the texture of the sampler (keeping its unit), or binds it to
+    // the lowest free unit
+    pub fn set_texture(&mut self, sampler_name: &str, texture: TextureKind) -> Result<(), String> {
+        if !self.bindings.has_unit(sampler_name) {
+            let unit = self.bindings.first_free_unit();
+            let max_units = crate::opengl::texture::max_combined_texture_image_units();
+            if unit >= max_units {
+                return Err(format!(
+                    "No texture unit left for sampler \"{}\" (the driver supports {})",
+                    sampler_name, max_units
+                ));
+            }
+            self.bindings.add(sampler_name, unit);
+        }
+
+        self.textures.insert(String::from(sampler_name), texture);
+        Ok(())
+    }
+
+    pub fn remove_texture(&mut self, sampler_name: &str) -> Option<TextureKind> {
+        self.bindings.remove(sampler_name);
+        self.textures.remove(sampler_name)
+    }
+
+    pub fn clear_textures(&mut self) {
+        self.bindings.clear();
+        self.textures.clear();
+    }
+
+    pub fn texture(&self, sampler_name: &str) -> Option<&TextureKind> {
+        self.textures.get(sampler_name)
     }
 
-    pub fn textures_mut(&mut self) -> &mut Vec<TextureKind> {
-        &mut self.textures
+    pub fn texture_mut(&mut self, sampler_name: &str) -> Option<&mut TextureKind> {
+        self.textures.get_mut(sampler_name)
+    }
+
+    pub fn textures(&self) -> std::collections::hash_map::Iter<'_, String, TextureKind> {
+        self.textures.iter()
+    }
+
+    pub fn texture_bindings(&self) -> &NamedTextureBindings {
+        &self.bindings
+    }
+
+    pub fn parameters_mut(&mut self) -> &mut MaterialParameters {
+        &mut self.parameters
+    }
+
+    pub fn render_state(&self) -> RenderState {
+        self.render_state
+    }
+
+    pub fn set_render_state(&mut self, render_state: RenderState) {
+        self.render_state = render_state;
     }
 }
 
 impl Material for TexturedMaterial {
     fn bind(&self) {
         self.shader_program.bind();
+        self.shader_program.set_uniforms(&self.parameters);
+        self.render_state.apply();
 
-        // Bind textures
-        for (unit, texture) in self.textures.iter().enumerate() {
-            crate::opengl::texture::set_active_texture_unit(unit as u32).unwrap();
-            texture.bind();
+        // Bind textures and point their samplers to the units
+        for (sampler_name, texture) in self.textures.iter() {
+            let unit = self.bindings.get(sampler_name).unwrap();
+            texture.bind_to_unit(unit).unwrap();
+            self.shader_program.set_int(sampler_name, unit as i32);
         }
     }
 
-    fn unbind(&self) {
-        // Unbind textures
-        for (unit, texture) in self.textures.iter().enumerate() {
-            crate::opengl::texture::set_active_texture_unit(unit as u32).unwrap();
-            texture.unbind();
-        }
+    fn shader_program(&self) -> &ShaderProgram {
+        &self.shader_program
+    }
 
-        crate::opengl::shader::shader_program::unbind();
+    fn parameters(&self) -> &MaterialParameters {
+        &self.parameters
     }
 
-    fn shader_program(&self) -> &ShaderProgram {
-        &self.shader_program
+    fn clone_with_parameters(&self, overrides: &MaterialParameters) -> Rc<dyn Material> {
+        Rc::new(Self {
+            shader_program: Rc::clone(&self.shader_program),
+            textures: self.textures.clone(),
+            bindings: self.bindings.clone(),
+            parameters: self.parameters.merged_with(overrides),
+            render_state: self.render_state,
+        })
     }
 }
@@ -1,22 +1,45 @@
 use std::rc::Rc;
 
-use crate::opengl::ebo::Ebo;
+use crate::opengl::ebo::{Ebo, IndexType};
 use crate::opengl::material::Material;
 use crate::opengl::vao::Vao;
 use crate::opengl::vbo::Vbo;
+use crate::opengl::vertex_layout::{Vertex, VertexLayout};
 
-use super::{Mesh, DrawMode};
+use super::{DrawMode, Mesh};
+
+// Vertex layout of one of the VBOs, set up once the material is known
+struct PendingLayout {
+    vbo_index: usize,
+    layout: VertexLayout,
+    per_instance: bool,
+}
 
 #[derive(Default)]
 pub struct MeshBuilder {
     material: Option<Rc<dyn Material>>,
     vao: Option<Vao>,
     vbos: Vec<Vbo>,
+    layouts: Vec<PendingLayout>,
 
     primitive_mode: Option<gl::types::GLenum>,
     vertex_count: Option<i32>,
+    // Draws instanced if set
+    instance_count: Option<i32>,
 
     ebo: Option<Ebo>,
+    index_type: Option<gl::types::GLenum>,
+}
+
+// The shader program is only looked up for named attributes, so layouts with
+// attribute indices work with materials without one (e.g. MockMaterial)
+fn attribute_locations(layout: &VertexLayout, material: &dyn Material) -> Result<Vec<u32>, String> {
+    let shader_program = layout
+        .has_named_attributes()
+        .then(|| material.shader_program());
+    layout
+        .attribute_locations(shader_program)
+        .map_err(|err| format!("Failed to set up vertex attributes: {}", err))
 }
 
 impl MeshBuilder {
@@ -32,9 +55,40 @@ impl MeshBuilder {
         self
     }
 
-    pub fn add_vbo<VboSetupFn>(mut self, setup: VboSetupFn) -> Self
+    // Named attributes are looked up in the material's shader program when
+    // the mesh is built. Sets the vertex count unless it was set.
+    pub fn add_vbo<V: Vertex>(self, vertices: &[V]) -> Self {
+        let mut builder = self.add_vbo_with_layout(vertices, V::layout(), false);
+        builder.vertex_count.get_or_insert(vertices.len() as i32);
+        builder
+    }
+
+    // Attributes advance once per instance instead of once per vertex, the
+    // mesh draws one instance per element
+    pub fn add_instance_vbo<V: Vertex>(self, instances: &[V]) -> Self {
+        let mut builder = self.add_vbo_with_layout(instances, V::layout(), true);
+        builder.instance_count = Some(instances.len() as i32);
+        builder
+    }
+
+    fn add_vbo_with_layout<V: Vertex>(
+        self,
+        vertices: &[V],
+        layout: VertexLayout,
+        per_instance: bool,
+    ) -> Self {
+        let mut builder = self.add_vbo_with_setup(|vbo| vbo.copy_data_static(vertices));
+        builder.layouts.push(PendingLayout {
+            vbo_index: builder.vbos.len() - 1,
+            layout,
+            per_instance,
+        });
+        builder
+    }
+
+    pub fn add_vbo_with_setup<VboSetupFn>(mut self, setup: VboSetupFn) -> Self
     where
-        VboSetupFn: FnMut(&mut Vbo)
+        VboSetupFn: FnMut(&mut Vbo),
     {
         let vao_ref = self.vao.as_ref().unwrap();
 
@@ -56,35 +110,94 @@ impl MeshBuilder {
         self
     }
 
-    pub fn set_indices(mut self, indices: &[u32]) -> Self {
+    pub fn set_indices<I: IndexType>(mut self, indices: &[I]) -> Self {
         let ebo = Ebo::new().setup(|ebo| {
-            ebo.copy_data_static(&indices);
+            ebo.copy_data_static(indices);
         });
         self.ebo = Some(ebo);
+        self.index_type = Some(I::GL_TYPE);
         self.vertex_count = Some(indices.len() as i32);
         self
     }
 
-    pub fn build(self) -> Mesh {
-        let primitive_mode = self.primitive_mode.expect("Draw mode for primitives was not set");
+    // Stores the indices with the smallest index type that fits all of them
+    pub fn set_compact_indices(self, indices: &[u32]) -> Self {
+        let max_index = indices.iter().copied().max().unwrap_or(0);
+        if u8::try_from(max_index).is_ok() {
+            let indices: Vec<u8> = indices.iter().map(|index| *index as u8).collect();
+            self.set_indices(&indices)
+        } else if u16::try_from(max_index).is_ok() {
+            let indices: Vec<u16> = indices.iter().map(|index| *index as u16).collect();
+            self.set_indices(&indices)
+        } else {
+            self.set_indices(indices)
+        }
+    }
+
+    // Fails if a named vertex attribute isn't active in the material's shader
+    pub fn build(mut self) -> Result<Mesh, String> {
+        let primitive_mode = self
+            .primitive_mode
+            .expect("Draw mode for primitives was not set");
+        let material = self.material.expect("Material was not set");
+        let vao = self.vao.unwrap();
+
+        vao.bind();
+        let layout_result: Result<(), String> = self.layouts.iter().try_for_each(|pending| {
+            let locations = attribute_locations(&pending.layout, material.as_ref())?;
 
-        let draw_mode = match self.ebo {
-            Some(ebo) => DrawMode::Elements {
+            let vbo = &mut self.vbos[pending.vbo_index];
+            vbo.bind();
+            vbo.set_vertex_layout(&pending.layout, &locations);
+            if pending.per_instance {
+                for location in locations.iter() {
+                    vbo.set_vertex_attrib_divisor(*location, 1);
+                }
+            }
+            Ok(())
+        });
+        crate::opengl::vao::unbind();
+        crate::opengl::buffers::unbind(gl::ARRAY_BUFFER);
+        layout_result?;
+
+        let draw_mode = match (self.ebo, self.instance_count) {
+            (Some(ebo), None) => DrawMode::Elements {
+                ebo,
+                elements_mode: primitive_mode,
+                vertices: self.vertex_count.unwrap(),
+                index_type: self.index_type.unwrap(),
+            },
+            (Some(ebo), Some(instances)) => DrawMode::ElementsInstanced {
                 ebo,
                 elements_mode: primitive_mode,
                 vertices: self.vertex_count.unwrap(),
+                index_type: self.index_type.unwrap(),
+                instances,
             },
-            None => DrawMode::Arrays {
+            (None, None) => DrawMode::Arrays {
                 array_mode: primitive_mode,
                 vertices: self.vertex_count.expect("Vertex count was not set"),
-            }
+            },
+            (None, Some(instances)) => DrawMode::ArraysInstanced {
+                array_mode: primitive_mode,
+                vertices: self.vertex_count.expect("Vertex count was not set"),
+                instances,
+            },
         };
-        let mesh = Mesh::new(
-            self.vao.unwrap(),
-            self.vbos,
-            self.material.expect("Material was not set"),
-            draw_mode,
-        );
-        mesh
+        Ok(Mesh::new(vao, self.vbos, material, draw_mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::opengl::material::MockMaterial;
+    use crate::opengl::mesh::factory::QuadVertex;
+
+    #[test]
+    fn index_located_attributes_dont_need_a_shader() {
+        let locations = attribute_locations(&QuadVertex::layout(), &MockMaterial).unwrap();
+        assert_eq!(locations, vec![0]);
     }
 }
@@ -5,22 +5,54 @@ use super::builder::MeshBuilder;
 use super::Mesh;
 
 use crate::opengl::material::Material;
+use crate::opengl::vertex_layout::Vertex;
+
+crate::vertex_struct! {
+    pub struct QuadVertex {
+        #[location = 0]
+        pub tex_coord: Vector2<f32>,
+    }
+}
+
+impl QuadVertex {
+    fn new(u: f32, v: f32) -> Self {
+        Self {
+            tex_coord: Vector2::new(u, v),
+        }
+    }
+}
 
 pub fn create_basic_quad_mesh(material: Rc<dyn Material>, tex_scale: f32) -> Mesh {
     MeshBuilder::new()
-        // Texture Coordinates
-        .add_vbo(|vbo| {
-            vbo.copy_data_static::<Vector2<f32>>(&[
-                Vector2::new(0.0, tex_scale),
-                Vector2::new(tex_scale, tex_scale),
-                Vector2::new(tex_scale, 0.0),
-                Vector2::new(0.0, 0.0),
-            ]);
-            vbo.set_basic_typed_vertex_attrib_pointer::<f32>(0, 2, gl::FLOAT, false);
-            vbo.set_vertex_attrib_enabled(0, true);
-        })
+        .add_vbo(&[
+            QuadVertex::new(0.0, tex_scale),
+            QuadVertex::new(tex_scale, tex_scale),
+            QuadVertex::new(tex_scale, 0.0),
+            QuadVertex::new(0.0, 0.0),
+        ])
         .set_primitive_mode(gl::TRIANGLES)
-        .set_indices(&[0, 1, 2, 0, 2, 3])
+        .set_indices::<u8>(&[0, 1, 2, 0, 2, 3])
+        .set_material(material)
+        .build()
+        .expect("Quad vertices only use attribute indices")
+}
+
+// Draws one unit quad per instance, the instance attributes (e.g. the tile
+// offset and scale) must not use location 0, which holds the quad corner
+pub fn create_instanced_quad_mesh<I: Vertex>(
+    material: Rc<dyn Material>,
+    instances: &[I],
+) -> Result<Mesh, String> {
+    MeshBuilder::new()
         .set_material(material)
+        .add_vbo(&[
+            QuadVertex::new(0.0, 1.0),
+            QuadVertex::new(1.0, 1.0),
+            QuadVertex::new(1.0, 0.0),
+            QuadVertex::new(0.0, 0.0),
+        ])
+        .add_instance_vbo(instances)
+        .set_primitive_mode(gl::TRIANGLES)
+        .set_indices::<u8>(&[0, 1, 2, 0, 2, 3])
         .build()
 }
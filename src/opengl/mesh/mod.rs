@@ -1,6 +1,7 @@
 use std::rc::Rc;
 
 use super::ebo::Ebo;
+use super::material::parameters::MaterialParameters;
 use super::material::Material;
 use super::vao::Vao;
 use super::vbo::Vbo;
@@ -17,6 +18,20 @@ pub enum DrawMode {
         ebo: Ebo,
         elements_mode: gl::types::GLenum,
         vertices: i32,
+        // GL_UNSIGNED_BYTE, GL_UNSIGNED_SHORT or GL_UNSIGNED_INT
+        index_type: gl::types::GLenum,
+    },
+    ArraysInstanced {
+        array_mode: gl::types::GLenum,
+        vertices: i32,
+        instances: i32,
+    },
+    ElementsInstanced {
+        ebo: Ebo,
+        elements_mode: gl::types::GLenum,
+        vertices: i32,
+        index_type: gl::types::GLenum,
+        instances: i32,
     },
 }
 
@@ -51,11 +66,34 @@ impl Mesh {
         }
     }
 
-    pub fn draw_with_material<PreDrawOp>(&self, material: &dyn Material, mut pre_draw_op: PreDrawOp)
+    pub fn draw_with_material<PreDrawOp>(&self, material: &dyn Material, pre_draw_op: PreDrawOp)
     where
         PreDrawOp: FnMut(&Self),
     {
+        self.draw_with_parameters(material, &MaterialParameters::new(), pre_draw_op);
+    }
+
+    // The overrides are set after the material's own parameters, for this
+    // draw only. Only parameters the material has can be overridden, since
+    // the shader program may be shared and the material's values are set
+    // again after the draw.
+    pub fn draw_with_parameters<PreDrawOp>(
+        &self,
+        material: &dyn Material,
+        overrides: &MaterialParameters,
+        mut pre_draw_op: PreDrawOp,
+    ) where
+        PreDrawOp: FnMut(&Self),
+    {
+        debug_assert!(
+            overrides
+                .names()
+                .all(|name| material.parameters().has(name)),
+            "Overridden parameters have to be set on the material"
+        );
+
         material.bind();
+        material.shader_program().set_uniforms(overrides);
         self.vao.bind();
 
         pre_draw_op(self);
@@ -72,21 +110,44 @@ impl Mesh {
                 ebo,
                 elements_mode,
                 vertices,
+                index_type,
             } => unsafe {
                 ebo.bind();
-                gl::DrawElements(
+                gl::DrawElements(*elements_mode, *vertices, *index_type, std::ptr::null());
+            },
+            DrawMode::ArraysInstanced {
+                array_mode,
+                vertices,
+                instances,
+            } => unsafe {
+                gl::DrawArraysInstanced(*array_mode, 0, *vertices, *instances);
+            },
+            DrawMode::ElementsInstanced {
+                ebo,
+                elements_mode,
+                vertices,
+                index_type,
+                instances,
+            } => unsafe {
+                ebo.bind();
+                gl::DrawElementsInstanced(
                     *elements_mode,
                     *vertices,
-                    gl::UNSIGNED_INT,
+                    *index_type,
                     std::ptr::null(),
+                    *instances,
                 );
-                super::buffers::unbind(gl::ELEMENT_ARRAY_BUFFER);
             },
         }
 
-        super::vao::unbind();
+        if !overrides.is_empty() {
+            let own_values = material.parameters().matching(overrides);
+            material.shader_program().set_uniforms(&own_values);
+        }
 
-        material.unbind();
+        // Everything is left bound, so drawing again with the same material
+        // or mesh skips the binds (see state_cache). The element array binding
+        // is part of the vertex array, so it stays with this mesh.
     }
 
     pub fn draw<PreDrawOp>(&self, pre_draw_op: PreDrawOp)
@@ -96,6 +157,10 @@ impl Mesh {
         self.draw_with_material(self.material.as_ref(), pre_draw_op);
     }
 
+    pub fn draw_mode(&self) -> &DrawMode {
+        &self.draw_mode
+    }
+
     pub fn material(&self) -> &dyn Material {
         self.material.as_ref()
     }
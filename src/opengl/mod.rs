@@ -1,8 +1,15 @@
 pub mod buffers;
+pub mod context;
 pub mod ebo;
+pub mod framebuffer;
 pub mod material;
 pub mod mesh;
+pub mod pbo;
+pub mod resource_tracker;
 pub mod shader;
+pub mod state_cache;
 pub mod texture;
+pub mod ubo;
 pub mod vao;
 pub mod vbo;
+pub mod vertex_layout;
@@ -0,0 +1,108 @@
+use std::rc::Rc;
+
+use nalgebra::Vector2;
+
+use super::buffers::{self, Buffer, PixelUnpackTarget, WriteOnly};
+use super::texture::texel_format::{map_texture_image, TexelFormat, TextureImage};
+use super::texture::texture_2d::{self, Texture2D};
+
+pub fn unbind() {
+    buffers::unbind(gl::PIXEL_UNPACK_BUFFER);
+}
+
+// Pixel unpack buffer, textures can be updated from it without client memory
+pub type Pbo = Buffer<PixelUnpackTarget>;
+
+// Image written into one of the buffers, waiting for the next flush
+struct StagedUpload {
+    buffer_index: usize,
+    texture: Rc<Texture2D>,
+    size: Vector2<i32>,
+    format: TexelFormat,
+    row_size: usize,
+}
+
+// Uploads through two pixel unpack buffers used in turns. Images are only
+// written into a mapped buffer when staged and the texture is updated from
+// it on the next flush (e.g. at the start of the next frame). The transfer
+// from the buffer happens on the GPU, while the next image is written into
+// the other buffer, so neither side waits for the other.
+pub struct PboUploader {
+    buffers: [Pbo; 2],
+    next_buffer_index: usize,
+    staged: Option<StagedUpload>,
+}
+
+impl PboUploader {
+    pub fn new() -> Self {
+        Self {
+            buffers: [Pbo::new(), Pbo::new()],
+            next_buffer_index: 0,
+            staged: None,
+        }
+    }
+
+    // Replaces an image that was staged but not flushed yet
+    pub fn stage(&mut self, image: TextureImage, texture: &Rc<Texture2D>) -> Result<(), String> {
+        let size = image.size();
+        if !texture.can_store(size.x, size.y, image.format().storage_format()) {
+            return Err(format!(
+                "Texture {} can't hold a {}x{} {} image",
+                texture.handle(),
+                size.x,
+                size.y,
+                image.format().name()
+            ));
+        }
+
+        let buffer_index = self.next_buffer_index;
+        let buffer = &mut self.buffers[buffer_index];
+
+        let bytes =
+            map_texture_image!(&image, image => std::mem::size_of_val(image.as_raw().as_slice()));
+        buffer.bind();
+        // Reallocating orphans the storage a previous transfer may still read
+        buffer.allocate(bytes, gl::STREAM_DRAW);
+        let write_result = buffer
+            .map_range::<WriteOnly>(0, bytes, gl::MAP_INVALIDATE_BUFFER_BIT)
+            .and_then(|mut mapping| {
+                map_texture_image!(&image, image => mapping.write(0, image.as_raw()));
+                mapping.unmap()
+            });
+        unbind();
+        write_result?;
+
+        self.staged = Some(StagedUpload {
+            buffer_index,
+            texture: Rc::clone(texture),
+            size,
+            format: image.format(),
+            row_size: image.row_size(),
+        });
+        self.next_buffer_index = 1 - buffer_index;
+        Ok(())
+    }
+
+    // Only queues the copy into the texture, so this returns before the GPU
+    // has the data
+    pub fn flush(&mut self) {
+        let Some(staged) = self.staged.take() else {
+            return;
+        };
+
+        let texture = &staged.texture;
+        texture.bind();
+        texture.set_swizzle_mask(staged.format.swizzle_mask());
+        super::texture::set_unpack_alignment(super::texture::alignment_for_row(staged.row_size));
+        texture.set_image_data_from_pbo(
+            &self.buffers[staged.buffer_index],
+            staged.size.x,
+            staged.size.y,
+            staged.format.storage_format(),
+            staged.format.source_format(),
+            staged.format.source_data_type(),
+        );
+        super::texture::set_unpack_alignment(4);
+        texture_2d::unbind();
+    }
+}
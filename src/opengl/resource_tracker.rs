@@ -0,0 +1,276 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// GL objects can only be used from the thread their context is current on,
+// so every thread keeps its own records
+thread_local! {
+    static TRACKER: RefCell<ResourceTracker> = RefCell::new(ResourceTracker::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Texture,
+    VertexBuffer,
+    ElementBuffer,
+    UniformBuffer,
+    PixelBuffer,
+    CopyBuffer,
+}
+
+impl ResourceKind {
+    pub const ALL: [ResourceKind; 6] = [
+        ResourceKind::Texture,
+        ResourceKind::VertexBuffer,
+        ResourceKind::ElementBuffer,
+        ResourceKind::UniformBuffer,
+        ResourceKind::PixelBuffer,
+        ResourceKind::CopyBuffer,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResourceKind::Texture => "textures",
+            ResourceKind::VertexBuffer => "vertex buffers",
+            ResourceKind::ElementBuffer => "element buffers",
+            ResourceKind::UniformBuffer => "uniform buffers",
+            ResourceKind::PixelBuffer => "pixel buffers",
+            ResourceKind::CopyBuffer => "copy buffers",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceCounts {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+struct ResourceTracker {
+    // Sizes are kept per part (e.g. cube map face), keyed by the GL target
+    resources: HashMap<(ResourceKind, u32), HashMap<gl::types::GLenum, usize>>,
+    texture_budget: Option<usize>,
+}
+
+impl ResourceTracker {
+    fn new() -> Self {
+        Self {
+            resources: HashMap::new(),
+            texture_budget: None,
+        }
+    }
+
+    fn counts(&self, kind: ResourceKind) -> ResourceCounts {
+        self.resources
+            .iter()
+            .filter(|((resource_kind, _), _)| *resource_kind == kind)
+            .fold(ResourceCounts::default(), |counts, (_, parts)| {
+                ResourceCounts {
+                    count: counts.count + 1,
+                    bytes: counts.bytes + parts.values().sum::<usize>(),
+                }
+            })
+    }
+}
+
+pub fn register(kind: ResourceKind, handle: u32) {
+    TRACKER.with(|tracker| {
+        tracker
+            .borrow_mut()
+            .resources
+            .insert((kind, handle), HashMap::new());
+    });
+}
+
+// Returns the size the resource had
+pub fn unregister(kind: ResourceKind, handle: u32) -> usize {
+    TRACKER.with(|tracker| {
+        tracker
+            .borrow_mut()
+            .resources
+            .remove(&(kind, handle))
+            .map_or(0, |parts| parts.values().sum())
+    })
+}
+
+// Replaces the estimated size of one part of the resource
+pub fn set_size(kind: ResourceKind, handle: u32, part: gl::types::GLenum, bytes: usize) {
+    TRACKER.with(|tracker| {
+        if let Some(parts) = tracker.borrow_mut().resources.get_mut(&(kind, handle)) {
+            parts.insert(part, bytes);
+        }
+    });
+}
+
+pub fn size(kind: ResourceKind, handle: u32) -> usize {
+    TRACKER.with(|tracker| {
+        tracker
+            .borrow()
+            .resources
+            .get(&(kind, handle))
+            .map_or(0, |parts| parts.values().sum())
+    })
+}
+
+pub fn counts(kind: ResourceKind) -> ResourceCounts {
+    TRACKER.with(|tracker| tracker.borrow().counts(kind))
+}
+
+pub fn total_bytes() -> usize {
+    ResourceKind::ALL
+        .iter()
+        .map(|kind| counts(*kind).bytes)
+        .sum()
+}
+
+pub fn texture_budget() -> Option<usize> {
+    TRACKER.with(|tracker| tracker.borrow().texture_budget)
+}
+
+pub fn set_texture_budget(texture_budget: Option<usize>) {
+    TRACKER.with(|tracker| tracker.borrow_mut().texture_budget = texture_budget);
+}
+
+pub fn is_over_texture_budget() -> bool {
+    texture_budget().is_some_and(|budget| counts(ResourceKind::Texture).bytes > budget)
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn summary() -> String {
+    let mut parts: Vec<String> = ResourceKind::ALL
+        .iter()
+        .map(|kind| {
+            let counts = counts(*kind);
+            format!(
+                "{} {} ({})",
+                counts.count,
+                kind.name(),
+                format_bytes(counts.bytes)
+            )
+        })
+        .collect();
+
+    if let Some(budget) = texture_budget() {
+        parts.push(format!("texture budget {}", format_bytes(budget)));
+    }
+
+    format!(
+        "GPU memory {}: {}",
+        format_bytes(total_bytes()),
+        parts.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every test runs on its own thread, so the records start out empty
+
+    #[test]
+    fn counts_sum_the_parts_of_each_resource() {
+        register(ResourceKind::Texture, 1);
+        register(ResourceKind::Texture, 2);
+        register(ResourceKind::VertexBuffer, 1);
+        set_size(
+            ResourceKind::Texture,
+            1,
+            gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            100,
+        );
+        set_size(
+            ResourceKind::Texture,
+            1,
+            gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+            50,
+        );
+        set_size(ResourceKind::Texture, 2, gl::TEXTURE_2D, 25);
+        set_size(ResourceKind::VertexBuffer, 1, gl::ARRAY_BUFFER, 8);
+
+        assert_eq!(size(ResourceKind::Texture, 1), 150);
+        assert_eq!(
+            counts(ResourceKind::Texture),
+            ResourceCounts {
+                count: 2,
+                bytes: 175
+            }
+        );
+        assert_eq!(
+            counts(ResourceKind::VertexBuffer),
+            ResourceCounts { count: 1, bytes: 8 }
+        );
+        assert_eq!(
+            counts(ResourceKind::UniformBuffer),
+            ResourceCounts::default()
+        );
+        assert_eq!(total_bytes(), 183);
+    }
+
+    #[test]
+    fn set_size_replaces_the_size_of_a_part() {
+        register(ResourceKind::PixelBuffer, 3);
+        set_size(ResourceKind::PixelBuffer, 3, gl::PIXEL_UNPACK_BUFFER, 100);
+        set_size(ResourceKind::PixelBuffer, 3, gl::PIXEL_UNPACK_BUFFER, 40);
+        assert_eq!(size(ResourceKind::PixelBuffer, 3), 40);
+    }
+
+    #[test]
+    fn unregistered_resources_are_ignored() {
+        set_size(ResourceKind::Texture, 4, gl::TEXTURE_2D, 100);
+        assert_eq!(size(ResourceKind::Texture, 4), 0);
+        assert_eq!(counts(ResourceKind::Texture).count, 0);
+        assert_eq!(unregister(ResourceKind::Texture, 4), 0);
+    }
+
+    #[test]
+    fn unregister_returns_the_size_and_forgets_the_resource() {
+        register(ResourceKind::Texture, 5);
+        register(ResourceKind::ElementBuffer, 5);
+        set_size(ResourceKind::Texture, 5, gl::TEXTURE_2D, 64);
+
+        assert_eq!(unregister(ResourceKind::Texture, 5), 64);
+        assert_eq!(counts(ResourceKind::Texture), ResourceCounts::default());
+        // Handles are only unique per kind
+        assert_eq!(counts(ResourceKind::ElementBuffer).count, 1);
+    }
+
+    #[test]
+    fn texture_budget_only_counts_textures() {
+        register(ResourceKind::Texture, 6);
+        register(ResourceKind::VertexBuffer, 6);
+        set_size(ResourceKind::Texture, 6, gl::TEXTURE_2D, 100);
+        set_size(ResourceKind::VertexBuffer, 6, gl::ARRAY_BUFFER, 1000);
+        assert!(!is_over_texture_budget());
+
+        set_texture_budget(Some(100));
+        assert!(!is_over_texture_budget());
+        set_texture_budget(Some(99));
+        assert!(is_over_texture_budget());
+    }
+
+    #[test]
+    fn format_bytes_uses_binary_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+        assert_eq!(format_bytes(2048 * 1024 * 1024 * 1024), "2048.0 GiB");
+    }
+}
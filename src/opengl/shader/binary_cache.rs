@@ -0,0 +1,305 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::shader_program::ShaderProgram;
+
+const CACHE_FILE_MAGIC: &[u8; 8] = b"IMDRIPPB";
+const CACHE_FILE_VERSION: u32 = 1;
+const CACHE_HEADER_SIZE: usize = 8 + 4 + 4 + 8 + 8;
+
+// FNV-1a is used instead of std's DefaultHasher, since the keys have to stay
+// the same across builds for the cache to be of any use
+pub struct Fnv1aHasher {
+    state: u64,
+}
+
+impl Fnv1aHasher {
+    pub fn new() -> Self {
+        Self {
+            state: 0xcbf29ce484222325,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes.iter() {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // Length-prefixed, so that ["ab", "c"] and ["a", "bc"] hash differently
+    pub fn write_str(&mut self, value: &str) {
+        self.write(&(value.len() as u64).to_le_bytes());
+        self.write(value.as_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1aHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[derive(Debug, Clone)]
+pub struct ProgramBinaryCache {
+    directory: PathBuf,
+}
+
+impl ProgramBinaryCache {
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn from_xdg_cache_dir() -> Option<Self> {
+        let cache_home = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+        Some(Self::new(cache_home.join("imdrip").join("shaders")))
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn is_supported() -> bool {
+        let has_program_binary = crate::opengl::context::is_version_at_least(4, 1)
+            || crate::opengl::context::has_extension("GL_ARB_get_program_binary");
+        if !has_program_binary {
+            return false;
+        }
+
+        unsafe {
+            let mut format_count = 0;
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut format_count);
+            format_count > 0
+        }
+    }
+
+    // Binaries are only valid for the driver that produced them, so the
+    // driver identification is part of the key
+    pub fn key_for_sources<'a, I>(sources: I) -> String
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut hasher = Fnv1aHasher::new();
+        hasher.write_str(&crate::opengl::context::get_string(gl::VENDOR));
+        hasher.write_str(&crate::opengl::context::get_string(gl::RENDERER));
+        hasher.write_str(&crate::opengl::context::get_string(gl::VERSION));
+
+        for source in sources {
+            hasher.write_str(source);
+        }
+
+        format!("{:016x}", hasher.finish())
+    }
+
+    fn path_for_key(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{}.bin", key))
+    }
+
+    pub fn load(&self, key: &str) -> Result<(u32, Vec<u8>), String> {
+        let path = self.path_for_key(key);
+        let data = std::fs::read(&path)
+            .map_err(|err| format!("Failed to read {}: {}", path.to_string_lossy(), err))?;
+
+        if data.len() < CACHE_HEADER_SIZE || &data[0..8] != CACHE_FILE_MAGIC {
+            return Err(format!(
+                "{} is not a program binary",
+                path.to_string_lossy()
+            ));
+        }
+
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        let read_u64 =
+            |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+        let file_version = read_u32(8);
+        let binary_format = read_u32(12);
+        let payload_length = read_u64(16) as usize;
+        let checksum = read_u64(24);
+
+        if file_version != CACHE_FILE_VERSION {
+            return Err(format!(
+                "{} has unsupported version {}",
+                path.to_string_lossy(),
+                file_version
+            ));
+        }
+
+        let payload = &data[CACHE_HEADER_SIZE..];
+        if payload.len() != payload_length || hash_bytes(payload) != checksum {
+            return Err(format!("{} is corrupted", path.to_string_lossy()));
+        }
+
+        Ok((binary_format, payload.to_vec()))
+    }
+
+    pub fn store(&self, key: &str, binary_format: u32, binary: &[u8]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.directory).map_err(|err| {
+            format!(
+                "Failed to create cache directory {}: {}",
+                self.directory.to_string_lossy(),
+                err
+            )
+        })?;
+
+        let mut data = Vec::with_capacity(CACHE_HEADER_SIZE + binary.len());
+        data.extend_from_slice(CACHE_FILE_MAGIC);
+        data.extend_from_slice(&CACHE_FILE_VERSION.to_le_bytes());
+        data.extend_from_slice(&binary_format.to_le_bytes());
+        data.extend_from_slice(&(binary.len() as u64).to_le_bytes());
+        data.extend_from_slice(&hash_bytes(binary).to_le_bytes());
+        data.extend_from_slice(binary);
+
+        // Write to a temporary file first, so that concurrently started
+        // instances never read a half-written binary
+        let path = self.path_for_key(key);
+        let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        let write_result = std::fs::File::create(&temp_path)
+            .and_then(|mut file| file.write_all(&data))
+            .and_then(|_| std::fs::rename(&temp_path, &path));
+
+        write_result.map_err(|err| {
+            let _ = std::fs::remove_file(&temp_path);
+            format!("Failed to write {}: {}", path.to_string_lossy(), err)
+        })
+    }
+
+    pub fn remove(&self, key: &str) {
+        let _ = std::fs::remove_file(self.path_for_key(key));
+    }
+
+    // Ok(None) on a cache miss. Unusable entries are removed and reported as
+    // errors, the program has to be built from source in both cases.
+    pub fn load_into_program(&self, key: &str) -> Result<Option<ShaderProgram>, String> {
+        let (binary_format, binary) = match self.load(key) {
+            Ok(cached) => cached,
+            // A missing entry is a plain cache miss, anything else is corrupted
+            Err(_) if !self.path_for_key(key).exists() => return Ok(None),
+            Err(err) => {
+                self.remove(key);
+                return Err(format!("Discarded cached shader program {}: {}", key, err));
+            }
+        };
+
+        let mut program = ShaderProgram::new();
+        match program.load_binary(binary_format, &binary) {
+            Ok(()) => Ok(Some(program)),
+            Err(err) => {
+                // The driver rejected the binary (e.g. after a driver update)
+                self.remove(key);
+                Err(format!("Discarded cached shader program {}: {}", key, err))
+            }
+        }
+    }
+
+    // Programs without a retrievable binary aren't cached
+    pub fn store_program(&self, key: &str, program: &ShaderProgram) -> Result<(), String> {
+        let Some((binary_format, binary)) = program.binary() else {
+            return Ok(());
+        };
+
+        self.store(key, binary_format, &binary)
+            .map_err(|err| format!("Failed to cache shader program: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str) -> ProgramBinaryCache {
+        ProgramBinaryCache::new(std::env::temp_dir().join(format!(
+            "imdrip-binary-cache-{}-{}",
+            name,
+            std::process::id()
+        )))
+    }
+
+    // Stores a binary and lets `corrupt` modify the written file
+    fn load_corrupted<F: FnOnce(&mut Vec<u8>)>(
+        name: &str,
+        corrupt: F,
+    ) -> Result<(u32, Vec<u8>), String> {
+        let cache = temp_cache(name);
+        cache.store("key", 7, b"binary").unwrap();
+
+        let path = cache.path_for_key("key");
+        let mut data = std::fs::read(&path).unwrap();
+        corrupt(&mut data);
+        std::fs::write(&path, data).unwrap();
+
+        let result = cache.load("key");
+        std::fs::remove_dir_all(cache.directory()).unwrap();
+        result
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(hash_bytes(b""), 0xcbf29ce484222325);
+        assert_eq!(hash_bytes(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash_bytes(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn strings_are_length_prefixed() {
+        let hash = |parts: &[&str]| {
+            let mut hasher = Fnv1aHasher::new();
+            for part in parts {
+                hasher.write_str(part);
+            }
+            hasher.finish()
+        };
+        assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+    }
+
+    #[test]
+    fn stored_binaries_are_loaded() {
+        let cache = temp_cache("round-trip");
+        cache.store("key", 0x1234, b"binary").unwrap();
+        let loaded = cache.load("key");
+        std::fs::remove_dir_all(cache.directory()).unwrap();
+
+        assert_eq!(loaded, Ok((0x1234, b"binary".to_vec())));
+    }
+
+    #[test]
+    fn missing_entries_fail_to_load() {
+        let cache = temp_cache("missing");
+        assert!(cache.load("key").unwrap_err().starts_with("Failed to read"));
+    }
+
+    #[test]
+    fn foreign_files_are_rejected() {
+        let err = load_corrupted("magic", |data| data[0] = b'X').unwrap_err();
+        assert!(err.ends_with("is not a program binary"), "{}", err);
+
+        let err =
+            load_corrupted("truncated", |data| data.truncate(CACHE_HEADER_SIZE - 1)).unwrap_err();
+        assert!(err.ends_with("is not a program binary"), "{}", err);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let err = load_corrupted("version", |data| data[8] += 1).unwrap_err();
+        assert!(err.ends_with("has unsupported version 2"), "{}", err);
+    }
+
+    #[test]
+    fn corrupted_payloads_are_rejected() {
+        let err = load_corrupted("checksum", |data| *data.last_mut().unwrap() ^= 1).unwrap_err();
+        assert!(err.ends_with("is corrupted"), "{}", err);
+
+        let err = load_corrupted("length", |data| data.push(0)).unwrap_err();
+        assert!(err.ends_with("is corrupted"), "{}", err);
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+
+use super::binary_cache::ProgramBinaryCache;
+use super::preprocessor::PreprocessedSource;
+use super::shader_part::ShaderPart;
+use super::shader_program::ShaderProgram;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    pub fn gl_kind(&self) -> gl::types::GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Compute => "compute",
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        match self {
+            ShaderStage::Vertex | ShaderStage::Fragment => true,
+            ShaderStage::Geometry => crate::opengl::context::is_version_at_least(3, 2),
+            ShaderStage::Compute => {
+                crate::opengl::context::is_version_at_least(4, 3)
+                    || crate::opengl::context::has_extension("GL_ARB_compute_shader")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StageCompileError {
+    pub stage: ShaderStage,
+    pub log: String,
+}
+
+#[derive(Debug)]
+pub enum ShaderProgramError {
+    NoStages,
+    UnsupportedStage(ShaderStage),
+    MixedComputeStages,
+    Compile(Vec<StageCompileError>),
+    Link(String),
+}
+
+impl fmt::Display for ShaderProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderProgramError::NoStages => write!(f, "Shader program has no stages"),
+            ShaderProgramError::UnsupportedStage(stage) => write!(
+                f,
+                "The current context doesn't support {} shaders",
+                stage.name()
+            ),
+            ShaderProgramError::MixedComputeStages => write!(
+                f,
+                "Compute shaders can't be linked together with other stages"
+            ),
+            ShaderProgramError::Compile(errors) => {
+                for error in errors.iter() {
+                    writeln!(f, "{} shader compilation failed:", error.stage.name())?;
+                    writeln!(f, "{}", error.log)?;
+                }
+                Ok(())
+            }
+            ShaderProgramError::Link(log) => write!(f, "Program linking failed:\n{}", log),
+        }
+    }
+}
+
+impl std::error::Error for ShaderProgramError {}
+
+struct StageSources {
+    stage: ShaderStage,
+    sources: Vec<String>,
+    preprocessed: Vec<PreprocessedSource>,
+}
+
+impl StageSources {
+    // #line source numbers are only unique within one preprocessed source, so
+    // logs can only be mapped back to files if the stage was built from one
+    fn resolve_log(&self, log: &str) -> String {
+        match self.preprocessed.as_slice() {
+            [preprocessed] if self.sources.len() == 1 => preprocessed.resolve_log(log),
+            _ => String::from(log),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShaderProgramBuilder {
+    stages: Vec<StageSources>,
+    attribute_bindings: Vec<(String, u32)>,
+    frag_data_bindings: Vec<(String, u32)>,
+    binary_cache: Option<ProgramBinaryCache>,
+}
+
+impl ShaderProgramBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn stage_mut(&mut self, stage: ShaderStage) -> &mut StageSources {
+        let index = match self
+            .stages
+            .iter()
+            .position(|sources| sources.stage == stage)
+        {
+            Some(index) => index,
+            None => {
+                self.stages.push(StageSources {
+                    stage,
+                    sources: Vec::new(),
+                    preprocessed: Vec::new(),
+                });
+                self.stages.len() - 1
+            }
+        };
+        &mut self.stages[index]
+    }
+
+    pub fn add_source(mut self, stage: ShaderStage, source: &str) -> Self {
+        self.stage_mut(stage).sources.push(String::from(source));
+        self
+    }
+
+    pub fn add_preprocessed_source(
+        mut self,
+        stage: ShaderStage,
+        source: PreprocessedSource,
+    ) -> Self {
+        let stage_sources = self.stage_mut(stage);
+        stage_sources.sources.push(String::from(source.source()));
+        stage_sources.preprocessed.push(source);
+        self
+    }
+
+    pub fn bind_attribute_location(mut self, name: &str, location: u32) -> Self {
+        self.attribute_bindings.push((String::from(name), location));
+        self
+    }
+
+    pub fn bind_frag_data_location(mut self, name: &str, color_number: u32) -> Self {
+        self.frag_data_bindings
+            .push((String::from(name), color_number));
+        self
+    }
+
+    pub fn use_binary_cache(mut self, binary_cache: Option<ProgramBinaryCache>) -> Self {
+        self.binary_cache = binary_cache;
+        self
+    }
+
+    fn binary_cache_key(&self) -> String {
+        let mut key_parts = Vec::new();
+        for stage_sources in self.stages.iter() {
+            key_parts.push(String::from(stage_sources.stage.name()));
+            key_parts.extend(stage_sources.sources.iter().cloned());
+        }
+        for (name, location) in self.attribute_bindings.iter() {
+            key_parts.push(format!("attribute {} {}", name, location));
+        }
+        for (name, color_number) in self.frag_data_bindings.iter() {
+            key_parts.push(format!("frag data {} {}", name, color_number));
+        }
+
+        ProgramBinaryCache::key_for_sources(key_parts.iter().map(|part| part.as_str()))
+    }
+
+    pub fn stage_sources(&self, stage: ShaderStage) -> Option<&[String]> {
+        self.stages
+            .iter()
+            .find(|sources| sources.stage == stage)
+            .map(|sources| sources.sources.as_slice())
+    }
+
+    // Binary cache errors are dropped, see build_with_cache_errors
+    pub fn build(self) -> Result<ShaderProgram, ShaderProgramError> {
+        self.build_with_cache_errors().map(|(program, _)| program)
+    }
+
+    // A failing binary cache doesn't fail the build, its errors are returned
+    // next to the program for the caller to report
+    pub fn build_with_cache_errors(
+        self,
+    ) -> Result<(ShaderProgram, Vec<String>), ShaderProgramError> {
+        if self.stages.is_empty() {
+            return Err(ShaderProgramError::NoStages);
+        }
+
+        let has_compute_stage = self
+            .stages
+            .iter()
+            .any(|sources| sources.stage == ShaderStage::Compute);
+        if has_compute_stage && self.stages.len() > 1 {
+            return Err(ShaderProgramError::MixedComputeStages);
+        }
+
+        if let Some(sources) = self
+            .stages
+            .iter()
+            .find(|sources| !sources.stage.is_supported())
+        {
+            return Err(ShaderProgramError::UnsupportedStage(sources.stage));
+        }
+
+        let binary_cache = self
+            .binary_cache
+            .as_ref()
+            .filter(|_| ProgramBinaryCache::is_supported());
+        let binary_cache_key = binary_cache.map(|_| self.binary_cache_key());
+        let mut cache_errors = Vec::new();
+
+        if let (Some(binary_cache), Some(key)) = (binary_cache, binary_cache_key.as_ref()) {
+            match binary_cache.load_into_program(key) {
+                Ok(Some(program)) => return Ok((program, cache_errors)),
+                Ok(None) => {}
+                Err(err) => cache_errors.push(err),
+            }
+        }
+
+        // Compile every stage before failing, so that all logs can be reported at once
+        let mut parts = Vec::new();
+        let mut compile_errors = Vec::new();
+        for stage_sources in self.stages.iter() {
+            let mut part = ShaderPart::new(stage_sources.stage.gl_kind());
+            let sources: Vec<&str> = stage_sources
+                .sources
+                .iter()
+                .map(|source| source.as_str())
+                .collect();
+            part.set_sources(&sources);
+
+            match part.compile() {
+                Ok(()) => parts.push(part),
+                Err(log) => compile_errors.push(StageCompileError {
+                    stage: stage_sources.stage,
+                    log: stage_sources.resolve_log(&log),
+                }),
+            }
+        }
+
+        if !compile_errors.is_empty() {
+            return Err(ShaderProgramError::Compile(compile_errors));
+        }
+
+        let mut program = ShaderProgram::new();
+        for (name, location) in self.attribute_bindings.iter() {
+            program.bind_attribute_location(name, *location);
+        }
+        for (name, color_number) in self.frag_data_bindings.iter() {
+            program.bind_frag_data_location(name, *color_number);
+        }
+
+        if binary_cache.is_some() {
+            program.set_binary_retrievable_hint(true);
+        }
+
+        program
+            .link_with_parts(&parts)
+            .map_err(ShaderProgramError::Link)?;
+
+        if let (Some(binary_cache), Some(key)) = (binary_cache, binary_cache_key.as_ref()) {
+            if let Err(err) = binary_cache.store_program(key, &program) {
+                cache_errors.push(err);
+            }
+        }
+
+        Ok((program, cache_errors))
+    }
+}
@@ -1,26 +1,64 @@
+pub mod binary_cache;
+pub mod builder;
+pub mod preprocessor;
+pub mod registry;
+pub mod reflection;
 pub mod shader_part;
 pub mod shader_program;
+pub mod uniform_block;
+pub mod uniform_value;
 
-use shader_part::*;
+use builder::*;
+use preprocessor::*;
 use shader_program::*;
 
-pub fn create_shader_from_parts(vert_source: &str, frag_source: &str) -> ShaderProgram {
-    let program = ShaderProgram::new().setup(|program| {
-        let mut vert_shader = ShaderPart::new(gl::VERTEX_SHADER);
-        vert_shader.set_source(vert_source);
-        vert_shader
-            .compile()
-            .expect("Vertex shader compilation failed");
+pub fn create_default_preprocessor() -> ShaderPreprocessor {
+    let mut preprocessor = ShaderPreprocessor::new();
+    preprocessor.add_embedded_source(
+        "checkerboard.glsl",
+        include_str!("../../shaders/include/checkerboard.glsl"),
+    );
+    preprocessor.add_embedded_source(
+        "color.glsl",
+        include_str!("../../shaders/include/color.glsl"),
+    );
+    preprocessor.add_embedded_source(
+        "tone_mapping.glsl",
+        include_str!("../../shaders/include/tone_mapping.glsl"),
+    );
+    preprocessor.add_embedded_source(
+        "display.glsl",
+        include_str!("../../shaders/include/display.glsl"),
+    );
+    preprocessor.add_embedded_source("quad.vert", include_str!("../../shaders/quad.vert"));
+    preprocessor.add_embedded_source("quad.frag", include_str!("../../shaders/quad.frag"));
+    preprocessor.add_embedded_source(
+        "panorama.frag",
+        include_str!("../../shaders/panorama.frag"),
+    );
+    preprocessor.add_embedded_source("volume.frag", include_str!("../../shaders/volume.frag"));
+    preprocessor.add_embedded_source(
+        "volume_montage.vert",
+        include_str!("../../shaders/volume_montage.vert"),
+    );
+    preprocessor
+}
 
-        let mut frag_shader = ShaderPart::new(gl::FRAGMENT_SHADER);
-        frag_shader.set_source(frag_source);
-        frag_shader
-            .compile()
-            .expect("Fragment shader compilation failed");
+pub fn create_shader_from_parts(vert_source: &str, frag_source: &str) -> ShaderProgram {
+    ShaderProgramBuilder::new()
+        .add_source(ShaderStage::Vertex, vert_source)
+        .add_source(ShaderStage::Fragment, frag_source)
+        .build()
+        .unwrap_or_else(|err| panic!("{}", err))
+}
 
-        program
-            .link_with_parts(&[vert_shader, frag_shader])
-            .expect("Program linking failed");
-    });
-    program
+pub fn create_shader_from_preprocessed_parts(
+    vert_source: PreprocessedSource,
+    frag_source: PreprocessedSource,
+) -> ShaderProgram {
+    ShaderProgramBuilder::new()
+        .add_preprocessed_source(ShaderStage::Vertex, vert_source)
+        .add_preprocessed_source(ShaderStage::Fragment, frag_source)
+        .build()
+        .unwrap_or_else(|err| panic!("{}", err))
 }
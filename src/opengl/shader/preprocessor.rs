@@ -0,0 +1,873 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct PreprocessedSource {
+    source: String,
+    file_names: Vec<String>,
+}
+
+impl PreprocessedSource {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn file_names(&self) -> &[String] {
+        &self.file_names
+    }
+
+    pub fn file_name(&self, source_index: usize) -> Option<&str> {
+        self.file_names.get(source_index).map(|name| name.as_str())
+    }
+
+    // Compile logs refer to source string numbers set by the #line directives
+    // (e.g. "0:12(5): error" on Mesa, "0(12) : error" on NVIDIA), so every line
+    // that starts with such a location gets its number replaced by the file name.
+    pub fn resolve_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.resolve_log_line(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn resolve_log_line(&self, line: &str) -> String {
+        let bytes = line.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() {
+            let at_boundary = start == 0 || bytes[start - 1].is_ascii_whitespace();
+            if at_boundary && bytes[start].is_ascii_digit() {
+                let index_end = start
+                    + bytes[start..]
+                        .iter()
+                        .take_while(|byte| byte.is_ascii_digit())
+                        .count();
+
+                let separator = bytes.get(index_end).copied();
+                let has_line_number = bytes
+                    .get(index_end + 1)
+                    .is_some_and(|byte| byte.is_ascii_digit());
+
+                if matches!(separator, Some(b':') | Some(b'(')) && has_line_number {
+                    let file_name = line[start..index_end]
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| self.file_name(index));
+
+                    if let Some(file_name) = file_name {
+                        return format!("{}{}{}", &line[..start], file_name, &line[index_end..]);
+                    }
+                }
+                return line.to_string();
+            }
+            start += 1;
+        }
+
+        line.to_string()
+    }
+}
+
+#[derive(Clone)]
+pub struct ShaderPreprocessor {
+    embedded_sources: HashMap<String, String>,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, String)>,
+}
+
+struct ProcessState {
+    output: String,
+    file_names: Vec<String>,
+    pragma_once_files: HashSet<String>,
+    include_stack: Vec<String>,
+    conditionals: Vec<Conditional>,
+    // Macros with a known state, None if they're known to be undefined.
+    // Others (e.g. GL_ES or extension macros) are only known to the compiler.
+    macros: HashMap<String, Option<String>>,
+}
+
+// Conditions are None if they can't be evaluated before compiling, lines in
+// such branches are processed, but errors are left to the compiler
+#[derive(Debug, Clone, Copy)]
+struct Conditional {
+    branch: Option<bool>,
+    // Whether an earlier branch of the same #if was taken
+    taken: Option<bool>,
+}
+
+impl Conditional {
+    fn new(condition: Option<bool>) -> Self {
+        Self {
+            branch: condition,
+            taken: condition,
+        }
+    }
+
+    fn next_branch(&mut self, condition: Option<bool>) {
+        self.branch = match self.taken {
+            Some(true) => Some(false),
+            Some(false) => condition,
+            None => condition.filter(|condition| !condition),
+        };
+        self.taken = match (self.taken, condition) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        };
+    }
+}
+
+impl ProcessState {
+    // Whether the current line is compiled, considering all enclosing #ifs
+    fn activity(&self) -> Option<bool> {
+        let mut activity = Some(true);
+        for conditional in self.conditionals.iter() {
+            match conditional.branch {
+                Some(false) => return Some(false),
+                None => activity = None,
+                Some(true) => {}
+            }
+        }
+        activity
+    }
+
+    fn is_defined(&self, name: &str) -> Option<bool> {
+        self.macros.get(name).map(|value| value.is_some())
+    }
+
+    fn handle_conditional(&mut self, directive: &str, arguments: &str) {
+        let first_word = arguments.split_whitespace().next().unwrap_or("");
+        match directive {
+            "if" => {
+                let condition = evaluate_condition(arguments, &self.macros);
+                self.conditionals.push(Conditional::new(condition));
+            }
+            "ifdef" => {
+                let condition = self.is_defined(first_word);
+                self.conditionals.push(Conditional::new(condition));
+            }
+            "ifndef" => {
+                let condition = self.is_defined(first_word).map(|defined| !defined);
+                self.conditionals.push(Conditional::new(condition));
+            }
+            "elif" => {
+                let condition = evaluate_condition(arguments, &self.macros);
+                if let Some(conditional) = self.conditionals.last_mut() {
+                    conditional.next_branch(condition);
+                }
+            }
+            "else" => {
+                if let Some(conditional) = self.conditionals.last_mut() {
+                    conditional.next_branch(Some(true));
+                }
+            }
+            "endif" => {
+                self.conditionals.pop();
+            }
+            "define" | "undef" => {
+                let name = first_word.split('(').next().unwrap_or("");
+                match self.activity() {
+                    Some(true) if directive == "define" => {
+                        let value = arguments.trim_start()[first_word.len()..].trim();
+                        self.macros
+                            .insert(String::from(name), Some(String::from(value)));
+                    }
+                    Some(true) => {
+                        self.macros.insert(String::from(name), None);
+                    }
+                    // Might or might not happen
+                    None => {
+                        self.macros.remove(name);
+                    }
+                    Some(false) => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+enum ResolvedInclude {
+    Embedded { name: String, source: String },
+    OnDisk { path: PathBuf, source: String },
+}
+
+impl ShaderPreprocessor {
+    pub fn new() -> Self {
+        Self {
+            embedded_sources: HashMap::new(),
+            include_dirs: Vec::new(),
+            defines: Vec::new(),
+        }
+    }
+
+    pub fn add_embedded_source(&mut self, name: &str, source: &str) {
+        self.embedded_sources
+            .insert(String::from(name), String::from(source));
+    }
+
+    pub fn add_include_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+    }
+
+    pub fn define(&mut self, name: &str, value: &str) {
+        match self
+            .defines
+            .iter_mut()
+            .find(|(existing, _)| existing == name)
+        {
+            Some((_, existing_value)) => *existing_value = String::from(value),
+            None => self.defines.push((String::from(name), String::from(value))),
+        }
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.defines.retain(|(existing, _)| existing != name);
+    }
+
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    pub fn process_embedded(&self, name: &str) -> Result<PreprocessedSource, String> {
+        let source = self
+            .embedded_sources
+            .get(name)
+            .ok_or_else(|| format!("No embedded shader source named \"{}\"", name))?;
+        self.process(name, source)
+    }
+
+    pub fn process_file<P: AsRef<Path>>(&self, path: P) -> Result<PreprocessedSource, String> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|err| {
+            format!(
+                "Failed to read shader source {}: {}",
+                path.to_string_lossy(),
+                err
+            )
+        })?;
+        self.process_with_dir(&path.to_string_lossy(), &source, path.parent())
+    }
+
+    pub fn process(&self, name: &str, source: &str) -> Result<PreprocessedSource, String> {
+        self.process_with_dir(name, source, None)
+    }
+
+    fn process_with_dir(
+        &self,
+        name: &str,
+        source: &str,
+        current_dir: Option<&Path>,
+    ) -> Result<PreprocessedSource, String> {
+        let mut state = ProcessState {
+            output: String::new(),
+            file_names: vec![String::from(name)],
+            pragma_once_files: HashSet::new(),
+            include_stack: vec![String::from(name)],
+            conditionals: Vec::new(),
+            macros: self
+                .defines
+                .iter()
+                .map(|(name, value)| (name.clone(), Some(value.clone())))
+                .collect(),
+        };
+
+        // #version has to stay the first statement, so the defines are
+        // injected right after it and the line numbering is reset afterwards
+        let lines: Vec<&str> = source.lines().collect();
+        let version_line = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with("#version"));
+
+        if let Some(version_line) = version_line {
+            state.output.push_str(lines[version_line].trim());
+            state.output.push('\n');
+        }
+
+        for (define_name, value) in self.defines.iter() {
+            state
+                .output
+                .push_str(&format!("#define {} {}\n", define_name, value));
+        }
+        state.output.push_str("#line 1 0\n");
+
+        self.process_lines(&lines, 0, version_line, current_dir, &mut state)?;
+
+        Ok(PreprocessedSource {
+            source: state.output,
+            file_names: state.file_names,
+        })
+    }
+
+    fn process_lines(
+        &self,
+        lines: &[&str],
+        source_index: usize,
+        skipped_line: Option<usize>,
+        current_dir: Option<&Path>,
+        state: &mut ProcessState,
+    ) -> Result<(), String> {
+        for (line_index, line) in lines.iter().enumerate() {
+            let trimmed = line.trim_start();
+
+            if Some(line_index) == skipped_line || trimmed.starts_with("#version") {
+                // Keep the line count intact for the following #line directives
+                state.output.push('\n');
+                continue;
+            }
+
+            let directive = parse_directive(trimmed);
+            let activity = state.activity();
+
+            if let Some((name, arguments)) = directive {
+                state.handle_conditional(name, arguments);
+            }
+
+            if directive.is_some_and(|(name, arguments)| {
+                name == "pragma" && arguments.split_whitespace().next() == Some("once")
+            }) {
+                if activity != Some(false) {
+                    let file_key = state.include_stack.last().unwrap().clone();
+                    state.pragma_once_files.insert(file_key);
+                }
+                state.output.push('\n');
+                continue;
+            }
+
+            if directive.is_some_and(|(name, _)| name == "include") {
+                // Includes in skipped branches might not exist at all
+                if activity == Some(false) {
+                    state.output.push('\n');
+                    continue;
+                }
+
+                let location = format!("{}:{}", state.file_names[source_index], line_index + 1);
+                let include_name = parse_include_name(trimmed).ok_or_else(|| {
+                    format!("{}: Malformed #include directive: {}", location, trimmed)
+                })?;
+
+                let resolved = match self.resolve_include(include_name, current_dir) {
+                    Ok(resolved) => resolved,
+                    // Whether the branch is compiled is only known to the
+                    // compiler, which reports the error if it is
+                    Err(err) if activity.is_none() => {
+                        let message = format!("{}: {}", location, err).replace('"', "'");
+                        state.output.push_str(&format!("#error {}\n", message));
+                        continue;
+                    }
+                    Err(err) => return Err(format!("{}: {}", location, err)),
+                };
+
+                self.process_include(include_name, resolved, state)
+                    .map_err(|err| format!("{}: {}", location, err))?;
+
+                state
+                    .output
+                    .push_str(&format!("#line {} {}\n", line_index + 2, source_index));
+                continue;
+            }
+
+            state.output.push_str(line);
+            state.output.push('\n');
+        }
+
+        Ok(())
+    }
+
+    fn process_include(
+        &self,
+        include_name: &str,
+        resolved: ResolvedInclude,
+        state: &mut ProcessState,
+    ) -> Result<(), String> {
+        let (file_key, include_dir, source) = match resolved {
+            ResolvedInclude::Embedded { name, source } => (name, None, source),
+            ResolvedInclude::OnDisk { path, source } => {
+                let include_dir = path.parent().map(|dir| dir.to_path_buf());
+                (path.to_string_lossy().into_owned(), include_dir, source)
+            }
+        };
+
+        if state.pragma_once_files.contains(&file_key) {
+            return Ok(());
+        }
+
+        if state.include_stack.contains(&file_key) {
+            return Err(format!("Recursive #include of \"{}\"", include_name));
+        }
+
+        let source_index = state.file_names.len();
+        state.file_names.push(file_key.clone());
+        state.include_stack.push(file_key);
+
+        state
+            .output
+            .push_str(&format!("#line 1 {}\n", source_index));
+        let lines: Vec<&str> = source.lines().collect();
+        self.process_lines(&lines, source_index, None, include_dir.as_deref(), state)?;
+
+        state.include_stack.pop();
+        Ok(())
+    }
+
+    fn resolve_include(
+        &self,
+        include_name: &str,
+        current_dir: Option<&Path>,
+    ) -> Result<ResolvedInclude, String> {
+        // Paths relative to the including file take precedence over the
+        // include directories, embedded sources are the last resort
+        let candidate_dirs = current_dir
+            .into_iter()
+            .chain(self.include_dirs.iter().map(|dir| dir.as_path()));
+        for dir in candidate_dirs {
+            let path = dir.join(include_name);
+            if path.is_file() {
+                let source = std::fs::read_to_string(&path).map_err(|err| {
+                    format!("Failed to read include {}: {}", path.to_string_lossy(), err)
+                })?;
+                let path = path.canonicalize().unwrap_or(path);
+                return Ok(ResolvedInclude::OnDisk { path, source });
+            }
+        }
+
+        if let Some(source) = self.embedded_sources.get(include_name) {
+            return Ok(ResolvedInclude::Embedded {
+                name: String::from(include_name),
+                source: source.clone(),
+            });
+        }
+
+        Err(format!("Unable to resolve #include \"{}\"", include_name))
+    }
+}
+
+// Splits "#name arguments" into the directive name and its arguments
+fn parse_directive(trimmed_line: &str) -> Option<(&str, &str)> {
+    let rest = trimmed_line.strip_prefix('#')?.trim_start();
+    let name_length = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    if name_length == 0 {
+        return None;
+    }
+
+    let arguments = &rest[name_length..];
+    // Comments after conditions aren't part of the expression
+    let arguments = arguments.split("//").next().unwrap_or("");
+    Some((&rest[..name_length], arguments.trim()))
+}
+
+fn parse_include_name(trimmed_line: &str) -> Option<&str> {
+    let start = trimmed_line.find(['"', '<'])?;
+    let closing = if trimmed_line[start..].starts_with('"') {
+        '"'
+    } else {
+        '>'
+    };
+    let name_start = start + 1;
+    let name_length = trimmed_line[name_start..].find(closing)?;
+    Some(&trimmed_line[name_start..name_start + name_length])
+}
+
+// Evaluates #if expressions made of integers, macros with known values,
+// defined(), !, comparisons, && and ||. None if the result depends on
+// something only the compiler knows.
+fn evaluate_condition(expression: &str, macros: &HashMap<String, Option<String>>) -> Option<bool> {
+    evaluate_expression(expression, macros, 0).map(|value| value != 0)
+}
+
+// Macro values are expressions themselves, the depth limits the expansion
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+fn evaluate_expression(
+    expression: &str,
+    macros: &HashMap<String, Option<String>>,
+    depth: usize,
+) -> Option<i64> {
+    if depth > MAX_EXPANSION_DEPTH {
+        return None;
+    }
+
+    let tokens = tokenize_expression(expression)?;
+    let mut parser = ExpressionParser {
+        tokens: &tokens,
+        position: 0,
+        macros,
+        depth,
+    };
+    let value = parser.parse_or().ok()?;
+    if parser.position != tokens.len() {
+        return None;
+    }
+    value
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Identifier(String),
+    Operator(&'static str),
+}
+
+const OPERATORS: [&str; 13] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", "+", "-",
+];
+
+fn tokenize_expression(expression: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while !rest.is_empty() {
+        let length;
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let literal = rest[..length].trim_end_matches(['u', 'U']);
+            let number = match literal.strip_prefix("0x").or(literal.strip_prefix("0X")) {
+                Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+                None => literal.parse().ok()?,
+            };
+            tokens.push(Token::Number(number));
+        } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Identifier(String::from(&rest[..length])));
+        } else {
+            let operator = OPERATORS
+                .iter()
+                .find(|operator| rest.starts_with(**operator))?;
+            length = operator.len();
+            tokens.push(Token::Operator(operator));
+        }
+        rest = rest[length..].trim_start();
+    }
+    Some(tokens)
+}
+
+// Values are None if they're unknown, Err means the expression isn't
+// supported (which makes it unknown as well)
+struct ExpressionParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    macros: &'a HashMap<String, Option<String>>,
+    depth: usize,
+}
+
+type ParsedValue = Result<Option<i64>, ()>;
+
+impl ExpressionParser<'_> {
+    fn next_is(&self, operator: &str) -> bool {
+        matches!(self.tokens.get(self.position), Some(Token::Operator(next)) if *next == operator)
+    }
+
+    fn eat(&mut self, operator: &str) -> bool {
+        let matches = self.next_is(operator);
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn parse_or(&mut self) -> ParsedValue {
+        let mut value = self.parse_and()?;
+        while self.eat("||") {
+            let right = self.parse_and()?;
+            value = match (value, right) {
+                (Some(left), _) if left != 0 => Some(1),
+                (_, Some(right)) if right != 0 => Some(1),
+                (Some(_), Some(_)) => Some(0),
+                _ => None,
+            };
+        }
+        Ok(value)
+    }
+
+    fn parse_and(&mut self) -> ParsedValue {
+        let mut value = self.parse_comparison()?;
+        while self.eat("&&") {
+            let right = self.parse_comparison()?;
+            value = match (value, right) {
+                (Some(0), _) | (_, Some(0)) => Some(0),
+                (Some(_), Some(_)) => Some(1),
+                _ => None,
+            };
+        }
+        Ok(value)
+    }
+
+    fn parse_comparison(&mut self) -> ParsedValue {
+        let mut value = self.parse_additive()?;
+        loop {
+            let operator = ["==", "!=", "<=", ">=", "<", ">"]
+                .into_iter()
+                .find(|operator| self.next_is(operator));
+            let Some(operator) = operator else {
+                return Ok(value);
+            };
+            self.position += 1;
+
+            let right = self.parse_additive()?;
+            value = value.zip(right).map(|(left, right)| {
+                let result = match operator {
+                    "==" => left == right,
+                    "!=" => left != right,
+                    "<=" => left <= right,
+                    ">=" => left >= right,
+                    "<" => left < right,
+                    _ => left > right,
+                };
+                result as i64
+            });
+        }
+    }
+
+    fn parse_additive(&mut self) -> ParsedValue {
+        let mut value = self.parse_unary()?;
+        loop {
+            let subtract = if self.eat("+") {
+                false
+            } else if self.eat("-") {
+                true
+            } else {
+                return Ok(value);
+            };
+
+            let right = self.parse_unary()?;
+            value = value.zip(right).map(|(left, right)| {
+                if subtract {
+                    left.wrapping_sub(right)
+                } else {
+                    left.wrapping_add(right)
+                }
+            });
+        }
+    }
+
+    fn parse_unary(&mut self) -> ParsedValue {
+        if self.eat("!") {
+            return Ok(self.parse_unary()?.map(|value| (value == 0) as i64));
+        }
+        if self.eat("-") {
+            return Ok(self.parse_unary()?.map(|value| value.wrapping_neg()));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> ParsedValue {
+        if self.eat("(") {
+            let value = self.parse_or()?;
+            return if self.eat(")") { Ok(value) } else { Err(()) };
+        }
+
+        let token = self.tokens.get(self.position).ok_or(())?.clone();
+        self.position += 1;
+        match token {
+            Token::Number(number) => Ok(Some(number)),
+            Token::Identifier(name) if name == "defined" => {
+                let parenthesized = self.eat("(");
+                let Some(Token::Identifier(name)) = self.tokens.get(self.position) else {
+                    return Err(());
+                };
+                self.position += 1;
+                if parenthesized && !self.eat(")") {
+                    return Err(());
+                }
+                Ok(self.macros.get(name).map(|value| value.is_some() as i64))
+            }
+            // Undefined identifiers evaluate to 0, like in C
+            Token::Identifier(name) => Ok(match self.macros.get(&name) {
+                Some(Some(value)) => evaluate_expression(value, self.macros, self.depth + 1),
+                Some(None) => Some(0),
+                None => None,
+            }),
+            Token::Operator(_) => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preprocessor_with(sources: &[(&str, &str)]) -> ShaderPreprocessor {
+        let mut preprocessor = ShaderPreprocessor::new();
+        for (name, source) in sources {
+            preprocessor.add_embedded_source(name, source);
+        }
+        preprocessor
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "imdrip-preprocessor-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn defines_are_injected_after_version() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.define("STEPS", "4");
+        preprocessor.define("STEPS", "8");
+
+        let processed = preprocessor
+            .process(
+                "main.frag",
+                "// header\n  #version 330 core\nvoid main() {}",
+            )
+            .unwrap();
+        assert_eq!(
+            processed.source(),
+            "#version 330 core\n#define STEPS 8\n#line 1 0\n// header\n\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn embedded_includes_get_line_directives() {
+        let preprocessor = preprocessor_with(&[("common.glsl", "float a;\nfloat b;")]);
+        let processed = preprocessor
+            .process(
+                "main.frag",
+                "#version 330 core\n#include \"common.glsl\"\nvoid main() {}",
+            )
+            .unwrap();
+
+        assert_eq!(
+            processed.source(),
+            "#version 330 core\n#line 1 0\n\n#line 1 1\nfloat a;\nfloat b;\n#line 3 0\nvoid main() {}\n"
+        );
+        assert_eq!(processed.file_names(), ["main.frag", "common.glsl"]);
+    }
+
+    #[test]
+    fn pragma_once_includes_a_file_once() {
+        let preprocessor = preprocessor_with(&[
+            ("common.glsl", "#pragma once\nfloat a;"),
+            ("lighting.glsl", "#include \"common.glsl\"\nfloat b;"),
+        ]);
+        let processed = preprocessor
+            .process(
+                "main.frag",
+                "#include \"common.glsl\"\n#include \"lighting.glsl\"\n#include <common.glsl>",
+            )
+            .unwrap();
+
+        assert_eq!(processed.source().matches("float a;").count(), 1);
+        assert_eq!(processed.source().matches("float b;").count(), 1);
+    }
+
+    #[test]
+    fn recursive_includes_are_errors() {
+        let preprocessor = preprocessor_with(&[
+            ("a.glsl", "#include \"b.glsl\""),
+            ("b.glsl", "\n#include \"a.glsl\""),
+        ]);
+        let err = preprocessor.process_embedded("a.glsl").unwrap_err();
+        assert_eq!(err, "a.glsl:1: b.glsl:2: Recursive #include of \"a.glsl\"");
+    }
+
+    #[test]
+    fn includes_on_disk_are_relative_to_the_including_file() {
+        let dir = temp_dir("relative");
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("main.frag"), "#include \"lib/util.glsl\"").unwrap();
+        std::fs::write(dir.join("lib/util.glsl"), "#include \"helper.glsl\"").unwrap();
+        std::fs::write(dir.join("lib/helper.glsl"), "float helper;").unwrap();
+        // Files next to the including one take precedence over embedded ones
+        let preprocessor = preprocessor_with(&[("helper.glsl", "float embedded;")]);
+
+        let processed = preprocessor.process_file(dir.join("main.frag")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(processed.source().contains("float helper;"));
+        assert!(!processed.source().contains("float embedded;"));
+        assert_eq!(processed.file_names().len(), 3);
+    }
+
+    #[test]
+    fn missing_includes_fail_in_active_code() {
+        let preprocessor = ShaderPreprocessor::new();
+        let err = preprocessor
+            .process(
+                "main.frag",
+                "#version 330 core\n\n#include \"missing.glsl\"",
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "main.frag:3: Unable to resolve #include \"missing.glsl\""
+        );
+    }
+
+    #[test]
+    fn includes_in_skipped_branches_are_not_resolved() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.define("LOW_QUALITY", "1");
+
+        let processed = preprocessor
+            .process(
+                "main.frag",
+                "#if 0\n#include \"a.glsl\"\n#endif\n\
+                 #ifndef LOW_QUALITY\n#include \"b.glsl\"\n#else\nfloat low;\n#endif\n\
+                 #define LEVEL 2\n#if LEVEL > 3 || !defined(LOW_QUALITY)\n#include \"c.glsl\"\n#endif",
+            )
+            .unwrap();
+        assert!(!processed.source().contains("#error"));
+        assert!(processed.source().contains("float low;"));
+    }
+
+    #[test]
+    fn includes_in_unknown_branches_are_left_to_the_compiler() {
+        let preprocessor = ShaderPreprocessor::new();
+        let processed = preprocessor
+            .process(
+                "main.frag",
+                "#ifdef GL_ES\n#include \"es.glsl\"\n#endif\nvoid main() {}",
+            )
+            .unwrap();
+        assert_eq!(
+            processed.source(),
+            "#line 1 0\n#ifdef GL_ES\n#error main.frag:2: Unable to resolve #include 'es.glsl'\n#endif\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn conditions_are_evaluated_with_known_macros() {
+        let mut macros = HashMap::new();
+        macros.insert(String::from("STEPS"), Some(String::from("4")));
+        macros.insert(String::from("TWICE"), Some(String::from("(STEPS * 2)")));
+        macros.insert(String::from("UNSET"), None);
+
+        assert_eq!(evaluate_condition("STEPS == 4", &macros), Some(true));
+        assert_eq!(evaluate_condition("STEPS - 1 >= 4", &macros), Some(false));
+        assert_eq!(
+            evaluate_condition("defined STEPS && !defined(UNSET)", &macros),
+            Some(true)
+        );
+        assert_eq!(evaluate_condition("UNSET", &macros), Some(false));
+        assert_eq!(evaluate_condition("0x10 > 15u", &macros), Some(true));
+        // Unknown macros only matter if they decide the result
+        assert_eq!(evaluate_condition("GL_ES", &macros), None);
+        assert_eq!(evaluate_condition("GL_ES || STEPS", &macros), Some(true));
+        assert_eq!(evaluate_condition("GL_ES && UNSET", &macros), Some(false));
+        // Unsupported operators make the condition unknown
+        assert_eq!(evaluate_condition("TWICE == 8", &macros), None);
+    }
+
+    #[test]
+    fn logs_refer_to_file_names() {
+        let preprocessor = preprocessor_with(&[("common.glsl", "float a;")]);
+        let processed = preprocessor
+            .process("main.frag", "#include \"common.glsl\"\nvoid main() {}")
+            .unwrap();
+
+        let log = "0:2(5): error: syntax error\nERROR: 1:1: 'a' redefined\n1 error";
+        assert_eq!(
+            processed.resolve_log(log),
+            "main.frag:2(5): error: syntax error\nERROR: common.glsl:1: 'a' redefined\n1 error"
+        );
+    }
+}
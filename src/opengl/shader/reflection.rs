@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveVariable {
+    pub location: i32,
+    pub gl_type: gl::types::GLenum,
+    pub size: i32,
+}
+
+pub const SAMPLER_TYPES: &[gl::types::GLenum] = &[
+    gl::SAMPLER_1D,
+    gl::SAMPLER_2D,
+    gl::SAMPLER_3D,
+    gl::SAMPLER_CUBE,
+    gl::SAMPLER_1D_SHADOW,
+    gl::SAMPLER_2D_SHADOW,
+    gl::SAMPLER_1D_ARRAY,
+    gl::SAMPLER_2D_ARRAY,
+    gl::SAMPLER_1D_ARRAY_SHADOW,
+    gl::SAMPLER_2D_ARRAY_SHADOW,
+    gl::SAMPLER_2D_MULTISAMPLE,
+    gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::SAMPLER_CUBE_SHADOW,
+    gl::SAMPLER_BUFFER,
+    gl::SAMPLER_2D_RECT,
+    gl::SAMPLER_2D_RECT_SHADOW,
+    gl::INT_SAMPLER_1D,
+    gl::INT_SAMPLER_2D,
+    gl::INT_SAMPLER_3D,
+    gl::INT_SAMPLER_CUBE,
+    gl::INT_SAMPLER_1D_ARRAY,
+    gl::INT_SAMPLER_2D_ARRAY,
+    gl::INT_SAMPLER_2D_MULTISAMPLE,
+    gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::INT_SAMPLER_BUFFER,
+    gl::INT_SAMPLER_2D_RECT,
+    gl::UNSIGNED_INT_SAMPLER_1D,
+    gl::UNSIGNED_INT_SAMPLER_2D,
+    gl::UNSIGNED_INT_SAMPLER_3D,
+    gl::UNSIGNED_INT_SAMPLER_CUBE,
+    gl::UNSIGNED_INT_SAMPLER_1D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_BUFFER,
+    gl::UNSIGNED_INT_SAMPLER_2D_RECT,
+];
+
+pub fn is_sampler_type(gl_type: gl::types::GLenum) -> bool {
+    SAMPLER_TYPES.contains(&gl_type)
+}
+
+pub fn gl_type_name(gl_type: gl::types::GLenum) -> &'static str {
+    match gl_type {
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        other if is_sampler_type(other) => "sampler",
+        _ => "unknown",
+    }
+}
+
+// Arrays are reported as "name[0]", they are stored under their plain name
+fn strip_array_suffix(name: &str) -> &str {
+    name.strip_suffix("[0]").unwrap_or(name)
+}
+
+fn read_active_name(name_buffer: &[u8], name_length: i32) -> String {
+    String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned()
+}
+
+pub fn query_active_uniforms(program_handle: u32) -> HashMap<String, ActiveVariable> {
+    let mut uniforms = HashMap::new();
+
+    unsafe {
+        let mut count = 0;
+        gl::GetProgramiv(program_handle, gl::ACTIVE_UNIFORMS, &mut count);
+
+        let mut max_name_length = 0;
+        gl::GetProgramiv(
+            program_handle,
+            gl::ACTIVE_UNIFORM_MAX_LENGTH,
+            &mut max_name_length,
+        );
+
+        let mut name_buffer: Vec<u8> = vec![0; max_name_length.max(1) as usize];
+        for index in 0..count as u32 {
+            let mut name_length = 0;
+            let mut size = 0;
+            let mut gl_type = 0;
+            gl::GetActiveUniform(
+                program_handle,
+                index,
+                name_buffer.len() as i32,
+                &mut name_length,
+                &mut size,
+                &mut gl_type,
+                name_buffer.as_mut_ptr() as *mut i8,
+            );
+
+            let name = read_active_name(&name_buffer, name_length);
+            let location =
+                gl::GetUniformLocation(program_handle, name_buffer.as_ptr() as *const i8);
+
+            // Uniforms inside of uniform blocks don't have a location
+            if location < 0 {
+                continue;
+            }
+
+            uniforms.insert(
+                String::from(strip_array_suffix(&name)),
+                ActiveVariable {
+                    location,
+                    gl_type,
+                    size,
+                },
+            );
+        }
+    }
+
+    uniforms
+}
+
+pub fn query_active_attributes(program_handle: u32) -> HashMap<String, ActiveVariable> {
+    let mut attributes = HashMap::new();
+
+    unsafe {
+        let mut count = 0;
+        gl::GetProgramiv(program_handle, gl::ACTIVE_ATTRIBUTES, &mut count);
+
+        let mut max_name_length = 0;
+        gl::GetProgramiv(
+            program_handle,
+            gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+            &mut max_name_length,
+        );
+
+        let mut name_buffer: Vec<u8> = vec![0; max_name_length.max(1) as usize];
+        for index in 0..count as u32 {
+            let mut name_length = 0;
+            let mut size = 0;
+            let mut gl_type = 0;
+            gl::GetActiveAttrib(
+                program_handle,
+                index,
+                name_buffer.len() as i32,
+                &mut name_length,
+                &mut size,
+                &mut gl_type,
+                name_buffer.as_mut_ptr() as *mut i8,
+            );
+
+            let name = read_active_name(&name_buffer, name_length);
+            let location = gl::GetAttribLocation(program_handle, name_buffer.as_ptr() as *const i8);
+
+            // Built-in attributes (gl_VertexID, ...) don't have a location
+            if location < 0 {
+                continue;
+            }
+
+            attributes.insert(
+                String::from(strip_array_suffix(&name)),
+                ActiveVariable {
+                    location,
+                    gl_type,
+                    size,
+                },
+            );
+        }
+    }
+
+    attributes
+}
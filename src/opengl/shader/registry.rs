@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::shader_program::ShaderProgram;
+
+pub struct ShaderRegistry {
+    shader_map: HashMap<String, Rc<ShaderProgram>>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        Self {
+            shader_map: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, shader_program: Rc<ShaderProgram>) {
+        self.shader_map.insert(String::from(name), shader_program);
+    }
+
+    pub fn get_clone_ref(&self, name: &str) -> Option<Rc<ShaderProgram>> {
+        self.shader_map.get(name).map(Rc::clone)
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.shader_map.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.shader_map.keys().map(|name| name.as_str())
+    }
+}
@@ -29,6 +29,27 @@ impl ShaderPart {
         }
     }
 
+    pub fn set_sources(&mut self, sources: &[&str]) {
+        unsafe {
+            use std::ffi::CString;
+            let sources: Vec<CString> = sources
+                .iter()
+                .map(|source| {
+                    CString::new(*source).expect("Source contained internal null byte(s)")
+                })
+                .collect();
+            let source_ptrs: Vec<*const i8> =
+                sources.iter().map(|source| source.as_ptr()).collect();
+
+            gl::ShaderSource(
+                self.handle,
+                source_ptrs.len() as i32,
+                source_ptrs.as_ptr(),
+                std::ptr::null()
+            );
+        }
+    }
+
     pub fn compile_status_code(&mut self) -> i32 {
         unsafe {
             let mut success = 0;
@@ -1,27 +1,45 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use super::reflection::{self, ActiveVariable};
 use super::shader_part::*;
+use super::uniform_block::{BindingPoint, NamedUniformBlockBindings};
+use super::uniform_value::{UniformSet, UniformValue};
 
 pub struct ShaderProgram {
     handle: u32,
+    uniforms: HashMap<String, ActiveVariable>,
+    attributes: HashMap<String, ActiveVariable>,
+
+    // Array elements ("lights[2]") aren't reported by glGetActiveUniform, so
+    // they are looked up on first use and cached here
+    element_uniforms: RefCell<HashMap<String, Option<ActiveVariable>>>,
+
+    #[cfg(debug_assertions)]
+    reported_uniforms: RefCell<HashSet<String>>,
 }
 
 pub fn unbind() {
-    unsafe {
-        gl::UseProgram(0);
-    }
+    crate::opengl::state_cache::use_program(0);
 }
 
 impl ShaderProgram {
     pub fn new() -> Self {
         unsafe {
             let handle = gl::CreateProgram();
-            Self { handle }
+            Self {
+                handle,
+                uniforms: HashMap::new(),
+                attributes: HashMap::new(),
+                element_uniforms: RefCell::new(HashMap::new()),
+                #[cfg(debug_assertions)]
+                reported_uniforms: RefCell::new(HashSet::new()),
+            }
         }
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::UseProgram(self.handle);
-        }
+        crate::opengl::state_cache::use_program(self.handle);
     }
 
     pub fn setup<SetupFn>(mut self, setup: SetupFn) -> Self
@@ -32,6 +50,28 @@ impl ShaderProgram {
         self
     }
 
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    pub fn bind_attribute_location(&mut self, name: &str, location: u32) {
+        unsafe {
+            use std::ffi::CString;
+            let name_cstr =
+                CString::new(name).expect("Attribute name contained internal null byte(s)");
+            gl::BindAttribLocation(self.handle, location, name_cstr.as_ptr());
+        }
+    }
+
+    pub fn bind_frag_data_location(&mut self, name: &str, color_number: u32) {
+        unsafe {
+            use std::ffi::CString;
+            let name_cstr =
+                CString::new(name).expect("Fragment output name contained internal null byte(s)");
+            gl::BindFragDataLocation(self.handle, color_number, name_cstr.as_ptr());
+        }
+    }
+
     pub fn link_status(&mut self) -> i32 {
         unsafe {
             let mut success = 0;
@@ -101,98 +141,285 @@ impl ShaderProgram {
                 return Err(err_log);
             }
 
+            self.reflect();
             Ok(())
         }
     }
 
-    #[allow(unused)]
-    pub fn get_uniform_location(&self, name: &str) -> i32 {
+    pub fn set_binary_retrievable_hint(&mut self, retrievable: bool) {
         unsafe {
+            let gl_value = if retrievable { gl::TRUE } else { gl::FALSE };
+            gl::ProgramParameteri(
+                self.handle,
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl_value as i32,
+            );
+        }
+    }
+
+    pub fn binary(&self) -> Option<(u32, Vec<u8>)> {
+        unsafe {
+            let mut binary_length = 0;
+            gl::GetProgramiv(self.handle, gl::PROGRAM_BINARY_LENGTH, &mut binary_length);
+            if binary_length <= 0 {
+                return None;
+            }
+
+            let mut binary: Vec<u8> = vec![0; binary_length as usize];
+            let mut written_length = 0;
+            let mut binary_format = 0;
+            gl::GetProgramBinary(
+                self.handle,
+                binary_length,
+                &mut written_length,
+                &mut binary_format,
+                binary.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+
+            if written_length <= 0 {
+                return None;
+            }
+
+            binary.truncate(written_length as usize);
+            Some((binary_format, binary))
+        }
+    }
+
+    pub fn load_binary(&mut self, binary_format: u32, binary: &[u8]) -> Result<(), String> {
+        unsafe {
+            gl::ProgramBinary(
+                self.handle,
+                binary_format,
+                binary.as_ptr() as *const std::ffi::c_void,
+                binary.len() as i32,
+            );
+
+            // Drivers report rejected binaries as link failures
+            if let Some(err_log) = self.link_log() {
+                return Err(err_log);
+            }
+
+            self.reflect();
+            Ok(())
+        }
+    }
+
+    fn reflect(&mut self) {
+        self.uniforms = reflection::query_active_uniforms(self.handle);
+        self.attributes = reflection::query_active_attributes(self.handle);
+        self.element_uniforms.borrow_mut().clear();
+    }
+
+    pub fn active_uniforms(&self) -> &HashMap<String, ActiveVariable> {
+        &self.uniforms
+    }
+
+    pub fn active_attributes(&self) -> &HashMap<String, ActiveVariable> {
+        &self.attributes
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<ActiveVariable> {
+        if let Some(uniform) = self.uniforms.get(name) {
+            return Some(*uniform);
+        }
+
+        if let Some(cached) = self.element_uniforms.borrow().get(name) {
+            return *cached;
+        }
+
+        let uniform = unsafe {
             use std::ffi::CString;
             let name_cstr =
                 CString::new(name).expect("Uniform name contained internal null byte(s)");
-            gl::GetUniformLocation(self.handle, name_cstr.as_ptr())
+            let location = gl::GetUniformLocation(self.handle, name_cstr.as_ptr());
+
+            // The element type is the one of the array the element belongs to
+            let array_name = name.split('[').next().unwrap_or(name);
+            self.uniforms
+                .get(array_name)
+                .filter(|_| location >= 0)
+                .map(|array| ActiveVariable {
+                    location,
+                    gl_type: array.gl_type,
+                    size: array.size,
+                })
+        };
+
+        self.element_uniforms
+            .borrow_mut()
+            .insert(String::from(name), uniform);
+        uniform
+    }
+
+    pub fn attribute_location(&self, name: &str) -> Option<i32> {
+        self.attributes
+            .get(name)
+            .map(|attribute| attribute.location)
+    }
+
+    pub fn uniform_block_index(&self, name: &str) -> Option<u32> {
+        let index = unsafe {
+            use std::ffi::CString;
+            let name_cstr =
+                CString::new(name).expect("Uniform block name contained internal null byte(s)");
+            gl::GetUniformBlockIndex(self.handle, name_cstr.as_ptr())
+        };
+        (index != gl::INVALID_INDEX).then_some(index)
+    }
+
+    pub fn set_uniform_block_binding(
+        &self,
+        name: &str,
+        binding_point: BindingPoint,
+    ) -> Result<(), String> {
+        let index = self.uniform_block_index(name).ok_or_else(|| {
+            format!(
+                "Uniform block \"{}\" is not active in shader program {}",
+                name, self.handle
+            )
+        })?;
+
+        unsafe {
+            gl::UniformBlockBinding(self.handle, index, binding_point);
+        }
+        Ok(())
+    }
+
+    // Blocks the program doesn't use are skipped
+    pub fn set_uniform_block_bindings(&self, bindings: &NamedUniformBlockBindings) {
+        for (name, binding_point) in bindings.iter() {
+            if let Some(index) = self.uniform_block_index(name) {
+                unsafe {
+                    gl::UniformBlockBinding(self.handle, index, *binding_point);
+                }
+            }
         }
     }
 
     #[allow(unused)]
-    pub fn set_bool(&self, name: &str, value: bool) {
+    pub fn get_uniform_location(&self, name: &str) -> i32 {
+        self.uniform(name).map_or(-1, |uniform| uniform.location)
+    }
+
+    #[cfg(debug_assertions)]
+    fn warn_once<MessageFn>(&self, name: &str, message: MessageFn)
+    where
+        MessageFn: FnOnce() -> String,
+    {
+        if self
+            .reported_uniforms
+            .borrow_mut()
+            .insert(String::from(name))
+        {
+            println!("Warning: {}", message());
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn warn_once<MessageFn>(&self, _name: &str, _message: MessageFn)
+    where
+        MessageFn: FnOnce() -> String,
+    {
+    }
+
+    pub fn set_uniform<V>(&self, name: &str, value: &V)
+    where
+        V: UniformValue + ?Sized,
+    {
+        let Some(uniform) = self.uniform(name) else {
+            self.warn_once(name, || {
+                format!(
+                    "Uniform \"{}\" is not active in shader program {}",
+                    name, self.handle
+                )
+            });
+            return;
+        };
+
+        if !value.matches_gl_type(uniform.gl_type) {
+            self.warn_once(name, || {
+                format!(
+                    "Uniform \"{}\" in shader program {} has type {}, but was set as {}",
+                    name,
+                    self.handle,
+                    reflection::gl_type_name(uniform.gl_type),
+                    value.glsl_type_name()
+                )
+            });
+            return;
+        }
+
+        if value.element_count() > uniform.size {
+            self.warn_once(name, || {
+                format!(
+                    "Uniform \"{}\" in shader program {} has {} element(s), but {} were set",
+                    name,
+                    self.handle,
+                    uniform.size,
+                    value.element_count()
+                )
+            });
+            return;
+        }
+
         unsafe {
-            let gl_value = if value { gl::TRUE } else { gl::FALSE };
-            gl::Uniform1i(self.get_uniform_location(name), gl_value as i32);
+            value.upload(uniform.location);
         }
     }
 
+    pub fn set_uniforms<S: UniformSet>(&self, uniforms: &S) {
+        uniforms.for_each_uniform(&mut |name, value| self.set_uniform(name, value));
+    }
+
+    #[allow(unused)]
+    pub fn set_bool(&self, name: &str, value: bool) {
+        self.set_uniform(name, &value);
+    }
+
     #[allow(unused)]
     pub fn set_int(&self, name: &str, value: i32) {
-        unsafe {
-            gl::Uniform1i(self.get_uniform_location(name), value);
-        }
+        self.set_uniform(name, &value);
     }
 
     #[allow(unused)]
     pub fn set_float(&self, name: &str, value: f32) {
-        unsafe {
-            gl::Uniform1f(self.get_uniform_location(name), value);
-        }
+        self.set_uniform(name, &value);
     }
 
     #[allow(unused)]
     pub fn set_vec2f(&self, name: &str, value: nalgebra::base::Vector2<f32>) {
-        unsafe {
-            gl::Uniform2f(self.get_uniform_location(name), value.x, value.y);
-        }
+        self.set_uniform(name, &value);
     }
 
     #[allow(unused)]
     pub fn set_vec2i(&self, name: &str, value: nalgebra::base::Vector2<i32>) {
-        unsafe {
-            gl::Uniform2i(self.get_uniform_location(name), value.x, value.y);
-        }
+        self.set_uniform(name, &value);
     }
 
     #[allow(unused)]
     pub fn set_vec3f(&self, name: &str, value: nalgebra::base::Vector3<f32>) {
-        unsafe {
-            gl::Uniform3f(self.get_uniform_location(name), value.x, value.y, value.z);
-        }
+        self.set_uniform(name, &value);
     }
 
     #[allow(unused)]
     pub fn set_vec3f_array(&self, name: &str, values: &[nalgebra::base::Vector3<f32>]) {
-        unsafe {
-            let ptr = values.as_ptr() as *const f32;
-            gl::Uniform3fv(self.get_uniform_location(name), values.len() as i32, ptr);
-        }
+        self.set_uniform(name, values);
     }
 
     #[allow(unused)]
     pub fn set_mat3f(&self, name: &str, value: nalgebra::base::Matrix3<f32>) {
-        unsafe {
-            gl::UniformMatrix3fv(
-                self.get_uniform_location(name),
-                1,
-                gl::FALSE,
-                value.as_ptr(),
-            );
-        }
+        self.set_uniform(name, &value);
     }
 
     #[allow(unused)]
     pub fn set_mat4f(&self, name: &str, value: nalgebra::base::Matrix4<f32>) {
-        unsafe {
-            gl::UniformMatrix4fv(
-                self.get_uniform_location(name),
-                1,
-                gl::FALSE,
-                value.as_ptr(),
-            )
-        }
+        self.set_uniform(name, &value);
     }
 }
 
 impl Drop for ShaderProgram {
     fn drop(&mut self) {
+        crate::opengl::state_cache::forget_program(self.handle);
         unsafe {
             gl::DeleteProgram(self.handle);
         }
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+
+pub type BindingPoint = u32;
+
+// Uniform blocks are bound to indexed binding points, which uniform buffers
+// are bound to as well. Shaders sharing a block name share its buffer.
+pub struct NamedUniformBlockBindings {
+    bindings: HashMap<String, BindingPoint>,
+}
+
+impl NamedUniformBlockBindings {
+    pub fn new() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    pub fn from<const N: usize>(key_values: [(String, BindingPoint); N]) -> Self {
+        let named_binding_points = HashMap::from(key_values);
+        Self {
+            bindings: named_binding_points,
+        }
+    }
+
+    pub fn add(&mut self, name: &str, binding_point: BindingPoint) {
+        let name = String::from(name);
+        self.bindings.insert(name, binding_point);
+    }
+
+    pub fn has_binding_point(&self, name: &str) -> bool {
+        self.bindings.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<BindingPoint> {
+        self.bindings.get(name).copied()
+    }
+
+    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, String, BindingPoint> {
+        self.bindings.iter()
+    }
+}
+
+// Types stored the way std140 lays them out, apart from their alignment,
+// which is checked at compile time by std140_block!. std140 bools are
+// 4 bytes large, so they're stored as u32.
+pub trait Std140: Copy {
+    const ALIGNMENT: usize;
+}
+
+macro_rules! impl_std140 {
+    ($($type:ty => $alignment:literal),* $(,)?) => {
+        $(
+            impl Std140 for $type {
+                const ALIGNMENT: usize = $alignment;
+            }
+        )*
+    };
+}
+
+impl_std140! {
+    f32 => 4,
+    i32 => 4,
+    u32 => 4,
+    Vector2<f32> => 8,
+    Vector2<i32> => 8,
+    Vector2<u32> => 8,
+    Vector3<f32> => 16,
+    Vector3<i32> => 16,
+    Vector3<u32> => 16,
+    Vector4<f32> => 16,
+    Vector4<i32> => 16,
+    Vector4<u32> => 16,
+    Matrix4<f32> => 16,
+}
+
+// Explicit padding, e.g. [u8; 8] to move the next field to a vec4 boundary
+impl<const N: usize> Std140 for [u8; N] {
+    const ALIGNMENT: usize = 1;
+}
+
+// Used by std140_block!, the modulo is kept out of the macro since padding
+// fields have an alignment of 1
+pub const fn is_aligned(offset: usize, alignment: usize) -> bool {
+    offset % alignment == 0
+}
+
+// Declares a struct matching a std140 uniform block with the same members.
+// Misaligned fields and sizes that aren't padded to a multiple of 16 bytes
+// fail to compile, padding has to be added as [u8; N] fields.
+#[macro_export]
+macro_rules! std140_block {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : $field_type:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        $vis struct $name {
+            $($field_vis $field: $field_type),*
+        }
+
+        $(
+            const _: () = assert!(
+                $crate::opengl::shader::uniform_block::is_aligned(
+                    std::mem::offset_of!($name, $field),
+                    <$field_type as $crate::opengl::shader::uniform_block::Std140>::ALIGNMENT,
+                ),
+                concat!(
+                    "std140 alignment of ",
+                    stringify!($name),
+                    "::",
+                    stringify!($field),
+                    " requires padding before it"
+                )
+            );
+        )*
+
+        const _: () = assert!(
+            std::mem::size_of::<$name>() % 16 == 0,
+            concat!(
+                "std140 size of ",
+                stringify!($name),
+                " requires padding to a multiple of 16 bytes"
+            )
+        );
+
+        // Nested structs are aligned like vec4s
+        impl $crate::opengl::shader::uniform_block::Std140 for $name {
+            const ALIGNMENT: usize = 16;
+        }
+    };
+}
@@ -0,0 +1,333 @@
+use nalgebra::base::{
+    Matrix2, Matrix2x3, Matrix2x4, Matrix3, Matrix3x2, Matrix3x4, Matrix4, Matrix4x2, Matrix4x3,
+    Vector2, Vector3, Vector4,
+};
+
+use super::reflection;
+
+pub trait UniformValue {
+    fn glsl_type_name(&self) -> &'static str;
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool;
+
+    fn element_count(&self) -> i32 {
+        1
+    }
+
+    // The program that owns `location` has to be bound
+    unsafe fn upload(&self, location: i32);
+}
+
+pub trait UniformArrayElement: UniformValue + Sized {
+    unsafe fn upload_slice(values: &[Self], location: i32);
+}
+
+impl<T: UniformArrayElement> UniformValue for [T] {
+    fn glsl_type_name(&self) -> &'static str {
+        self.first().map_or("array", |value| value.glsl_type_name())
+    }
+
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+        self.first()
+            .map_or(true, |value| value.matches_gl_type(gl_type))
+    }
+
+    fn element_count(&self) -> i32 {
+        self.len() as i32
+    }
+
+    unsafe fn upload(&self, location: i32) {
+        T::upload_slice(self, location);
+    }
+}
+
+impl<T: UniformArrayElement, const N: usize> UniformValue for [T; N] {
+    fn glsl_type_name(&self) -> &'static str {
+        self.as_slice().glsl_type_name()
+    }
+
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+        self.as_slice().matches_gl_type(gl_type)
+    }
+
+    fn element_count(&self) -> i32 {
+        N as i32
+    }
+
+    unsafe fn upload(&self, location: i32) {
+        T::upload_slice(self, location);
+    }
+}
+
+impl<T: UniformArrayElement> UniformValue for Vec<T> {
+    fn glsl_type_name(&self) -> &'static str {
+        self.as_slice().glsl_type_name()
+    }
+
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+        self.as_slice().matches_gl_type(gl_type)
+    }
+
+    fn element_count(&self) -> i32 {
+        self.len() as i32
+    }
+
+    unsafe fn upload(&self, location: i32) {
+        T::upload_slice(self, location);
+    }
+}
+
+impl UniformValue for bool {
+    fn glsl_type_name(&self) -> &'static str {
+        "bool"
+    }
+
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+        matches!(gl_type, gl::BOOL | gl::INT)
+    }
+
+    unsafe fn upload(&self, location: i32) {
+        let gl_value = if *self { gl::TRUE } else { gl::FALSE };
+        gl::Uniform1i(location, gl_value as i32);
+    }
+}
+
+impl UniformArrayElement for bool {
+    unsafe fn upload_slice(values: &[Self], location: i32) {
+        let int_values: Vec<i32> = values.iter().map(|value| *value as i32).collect();
+        gl::Uniform1iv(location, int_values.len() as i32, int_values.as_ptr());
+    }
+}
+
+// Sampler uniforms are set through their texture unit index
+impl UniformValue for i32 {
+    fn glsl_type_name(&self) -> &'static str {
+        "int"
+    }
+
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+        matches!(gl_type, gl::INT | gl::BOOL) || reflection::is_sampler_type(gl_type)
+    }
+
+    unsafe fn upload(&self, location: i32) {
+        gl::Uniform1i(location, *self);
+    }
+}
+
+impl UniformArrayElement for i32 {
+    unsafe fn upload_slice(values: &[Self], location: i32) {
+        gl::Uniform1iv(location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformValue for u32 {
+    fn glsl_type_name(&self) -> &'static str {
+        "uint"
+    }
+
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+        matches!(gl_type, gl::UNSIGNED_INT | gl::BOOL)
+    }
+
+    unsafe fn upload(&self, location: i32) {
+        gl::Uniform1ui(location, *self);
+    }
+}
+
+impl UniformArrayElement for u32 {
+    unsafe fn upload_slice(values: &[Self], location: i32) {
+        gl::Uniform1uiv(location, values.len() as i32, values.as_ptr());
+    }
+}
+
+impl UniformValue for f32 {
+    fn glsl_type_name(&self) -> &'static str {
+        "float"
+    }
+
+    fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+        gl_type == gl::FLOAT
+    }
+
+    unsafe fn upload(&self, location: i32) {
+        gl::Uniform1f(location, *self);
+    }
+}
+
+impl UniformArrayElement for f32 {
+    unsafe fn upload_slice(values: &[Self], location: i32) {
+        gl::Uniform1fv(location, values.len() as i32, values.as_ptr());
+    }
+}
+
+macro_rules! impl_vector_uniform {
+    ($vector:ty, $scalar:ty, $name:literal, $upload_fn:path, [$($gl_type:path),+]) => {
+        impl UniformValue for $vector {
+            fn glsl_type_name(&self) -> &'static str {
+                $name
+            }
+
+            fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+                matches!(gl_type, $($gl_type)|+)
+            }
+
+            unsafe fn upload(&self, location: i32) {
+                $upload_fn(location, 1, self.as_ptr());
+            }
+        }
+
+        impl UniformArrayElement for $vector {
+            unsafe fn upload_slice(values: &[Self], location: i32) {
+                $upload_fn(location, values.len() as i32, values.as_ptr() as *const $scalar);
+            }
+        }
+    };
+}
+
+impl_vector_uniform!(Vector2<f32>, f32, "vec2", gl::Uniform2fv, [gl::FLOAT_VEC2]);
+impl_vector_uniform!(Vector3<f32>, f32, "vec3", gl::Uniform3fv, [gl::FLOAT_VEC3]);
+impl_vector_uniform!(Vector4<f32>, f32, "vec4", gl::Uniform4fv, [gl::FLOAT_VEC4]);
+
+impl_vector_uniform!(
+    Vector2<i32>,
+    i32,
+    "ivec2",
+    gl::Uniform2iv,
+    [gl::INT_VEC2, gl::BOOL_VEC2]
+);
+impl_vector_uniform!(
+    Vector3<i32>,
+    i32,
+    "ivec3",
+    gl::Uniform3iv,
+    [gl::INT_VEC3, gl::BOOL_VEC3]
+);
+impl_vector_uniform!(
+    Vector4<i32>,
+    i32,
+    "ivec4",
+    gl::Uniform4iv,
+    [gl::INT_VEC4, gl::BOOL_VEC4]
+);
+
+impl_vector_uniform!(
+    Vector2<u32>,
+    u32,
+    "uvec2",
+    gl::Uniform2uiv,
+    [gl::UNSIGNED_INT_VEC2, gl::BOOL_VEC2]
+);
+impl_vector_uniform!(
+    Vector3<u32>,
+    u32,
+    "uvec3",
+    gl::Uniform3uiv,
+    [gl::UNSIGNED_INT_VEC3, gl::BOOL_VEC3]
+);
+impl_vector_uniform!(
+    Vector4<u32>,
+    u32,
+    "uvec4",
+    gl::Uniform4uiv,
+    [gl::UNSIGNED_INT_VEC4, gl::BOOL_VEC4]
+);
+
+// nalgebra names matrices by rows x columns while GLSL uses columns x rows,
+// both store them in column-major order
+macro_rules! impl_matrix_uniform {
+    ($matrix:ty, $name:literal, $upload_fn:path, $gl_type:path) => {
+        impl UniformValue for $matrix {
+            fn glsl_type_name(&self) -> &'static str {
+                $name
+            }
+
+            fn matches_gl_type(&self, gl_type: gl::types::GLenum) -> bool {
+                gl_type == $gl_type
+            }
+
+            unsafe fn upload(&self, location: i32) {
+                $upload_fn(location, 1, gl::FALSE, self.as_ptr());
+            }
+        }
+
+        impl UniformArrayElement for $matrix {
+            unsafe fn upload_slice(values: &[Self], location: i32) {
+                $upload_fn(
+                    location,
+                    values.len() as i32,
+                    gl::FALSE,
+                    values.as_ptr() as *const f32,
+                );
+            }
+        }
+    };
+}
+
+impl_matrix_uniform!(Matrix2<f32>, "mat2", gl::UniformMatrix2fv, gl::FLOAT_MAT2);
+impl_matrix_uniform!(Matrix3<f32>, "mat3", gl::UniformMatrix3fv, gl::FLOAT_MAT3);
+impl_matrix_uniform!(Matrix4<f32>, "mat4", gl::UniformMatrix4fv, gl::FLOAT_MAT4);
+impl_matrix_uniform!(
+    Matrix3x2<f32>,
+    "mat2x3",
+    gl::UniformMatrix2x3fv,
+    gl::FLOAT_MAT2x3
+);
+impl_matrix_uniform!(
+    Matrix4x2<f32>,
+    "mat2x4",
+    gl::UniformMatrix2x4fv,
+    gl::FLOAT_MAT2x4
+);
+impl_matrix_uniform!(
+    Matrix2x3<f32>,
+    "mat3x2",
+    gl::UniformMatrix3x2fv,
+    gl::FLOAT_MAT3x2
+);
+impl_matrix_uniform!(
+    Matrix4x3<f32>,
+    "mat3x4",
+    gl::UniformMatrix3x4fv,
+    gl::FLOAT_MAT3x4
+);
+impl_matrix_uniform!(
+    Matrix2x4<f32>,
+    "mat4x2",
+    gl::UniformMatrix4x2fv,
+    gl::FLOAT_MAT4x2
+);
+impl_matrix_uniform!(
+    Matrix3x4<f32>,
+    "mat4x3",
+    gl::UniformMatrix4x3fv,
+    gl::FLOAT_MAT4x3
+);
+
+pub trait UniformSet {
+    fn for_each_uniform(&self, apply: &mut dyn FnMut(&str, &dyn UniformValue));
+}
+
+// Declares a struct whose fields are uploaded by ShaderProgram::set_uniforms,
+// each field is set on the uniform with the same name
+#[macro_export]
+macro_rules! uniform_set {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : $field_type:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $field_type),*
+        }
+
+        impl $crate::opengl::shader::uniform_value::UniformSet for $name {
+            fn for_each_uniform(
+                &self,
+                apply: &mut dyn FnMut(&str, &dyn $crate::opengl::shader::uniform_value::UniformValue),
+            ) {
+                $(apply(stringify!($field), &self.$field);)*
+            }
+        }
+    };
+}
@@ -0,0 +1,268 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// Every thread has its own context, so the bindings are mirrored per thread
+thread_local! {
+    static STATE: RefCell<GlState> = RefCell::new(GlState::default());
+}
+
+// Mirror of the bindings of the current context. Bindings that aren't known
+// (e.g. before the first bind) are always set, so everything that binds
+// objects has to go through the functions below. Debug builds check the
+// actual binding whenever a call is skipped.
+#[derive(Default)]
+struct GlState {
+    program: Option<u32>,
+    vertex_array: Option<u32>,
+    // Keyed by the vertex array, since the element array binding is part of
+    // the vertex array state
+    element_buffers: HashMap<u32, u32>,
+    buffers: HashMap<gl::types::GLenum, u32>,
+    active_texture_unit: Option<u32>,
+    // Keyed by texture unit and target
+    textures: HashMap<(u32, gl::types::GLenum), u32>,
+    capabilities: HashMap<gl::types::GLenum, bool>,
+}
+
+impl GlState {
+    fn buffer_binding(&mut self, target: gl::types::GLenum) -> Option<&mut u32> {
+        if target != gl::ELEMENT_ARRAY_BUFFER {
+            return Some(self.buffers.entry(target).or_insert(UNKNOWN));
+        }
+
+        let vertex_array = self.vertex_array?;
+        Some(self.element_buffers.entry(vertex_array).or_insert(UNKNOWN))
+    }
+}
+
+// No object has this handle, so it never matches a binding
+const UNKNOWN: u32 = u32::MAX;
+
+// Records the new value and returns whether it differs from the cached one
+fn update(cached: &mut u32, value: u32) -> bool {
+    let changed = *cached != value;
+    *cached = value;
+    changed
+}
+
+fn update_option(cached: &mut Option<u32>, value: u32) -> bool {
+    update(cached.get_or_insert(UNKNOWN), value)
+}
+
+fn with_state<R>(apply: impl FnOnce(&mut GlState) -> R) -> R {
+    STATE.with(|state| apply(&mut state.borrow_mut()))
+}
+
+fn debug_check_binding(query: Option<gl::types::GLenum>, expected: u32, description: &str) {
+    let Some(query) = query else {
+        return;
+    };
+    if !cfg!(debug_assertions) {
+        return;
+    }
+
+    let mut actual = 0;
+    unsafe {
+        gl::GetIntegerv(query, &mut actual);
+    }
+    assert_eq!(
+        actual as u32, expected,
+        "{} was changed without going through the state cache",
+        description
+    );
+}
+
+fn buffer_binding_query(target: gl::types::GLenum) -> Option<gl::types::GLenum> {
+    match target {
+        gl::ARRAY_BUFFER => Some(gl::ARRAY_BUFFER_BINDING),
+        gl::ELEMENT_ARRAY_BUFFER => Some(gl::ELEMENT_ARRAY_BUFFER_BINDING),
+        gl::UNIFORM_BUFFER => Some(gl::UNIFORM_BUFFER_BINDING),
+        gl::PIXEL_PACK_BUFFER => Some(gl::PIXEL_PACK_BUFFER_BINDING),
+        gl::PIXEL_UNPACK_BUFFER => Some(gl::PIXEL_UNPACK_BUFFER_BINDING),
+        gl::COPY_READ_BUFFER => Some(gl::COPY_READ_BUFFER_BINDING),
+        gl::COPY_WRITE_BUFFER => Some(gl::COPY_WRITE_BUFFER_BINDING),
+        _ => None,
+    }
+}
+
+fn texture_binding_query(target: gl::types::GLenum) -> Option<gl::types::GLenum> {
+    match target {
+        gl::TEXTURE_2D => Some(gl::TEXTURE_BINDING_2D),
+        gl::TEXTURE_2D_ARRAY => Some(gl::TEXTURE_BINDING_2D_ARRAY),
+        gl::TEXTURE_3D => Some(gl::TEXTURE_BINDING_3D),
+        gl::TEXTURE_CUBE_MAP => Some(gl::TEXTURE_BINDING_CUBE_MAP),
+        _ => None,
+    }
+}
+
+pub fn use_program(handle: u32) {
+    if with_state(|state| update_option(&mut state.program, handle)) {
+        unsafe {
+            gl::UseProgram(handle);
+        }
+    } else {
+        debug_check_binding(Some(gl::CURRENT_PROGRAM), handle, "The current program");
+    }
+}
+
+pub fn bind_vertex_array(handle: u32) {
+    if with_state(|state| update_option(&mut state.vertex_array, handle)) {
+        unsafe {
+            gl::BindVertexArray(handle);
+        }
+    } else {
+        debug_check_binding(
+            Some(gl::VERTEX_ARRAY_BINDING),
+            handle,
+            "The vertex array binding",
+        );
+    }
+}
+
+pub fn bind_buffer(target: gl::types::GLenum, handle: u32) {
+    let changed = with_state(|state| match state.buffer_binding(target) {
+        Some(cached) => update(cached, handle),
+        None => true,
+    });
+
+    if changed {
+        unsafe {
+            gl::BindBuffer(target, handle);
+        }
+    } else {
+        debug_check_binding(buffer_binding_query(target), handle, "A buffer binding");
+    }
+}
+
+// For calls that bind a buffer as a side effect, e.g. glBindBufferBase
+pub fn record_buffer_binding(target: gl::types::GLenum, handle: u32) {
+    with_state(|state| {
+        if let Some(cached) = state.buffer_binding(target) {
+            *cached = handle;
+        }
+    });
+}
+
+pub fn active_texture(unit: u32) {
+    if with_state(|state| update_option(&mut state.active_texture_unit, unit)) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+        }
+    } else {
+        debug_check_binding(
+            Some(gl::ACTIVE_TEXTURE),
+            gl::TEXTURE0 + unit,
+            "The active texture unit",
+        );
+    }
+}
+
+// Binds to the active texture unit
+pub fn bind_texture(target: gl::types::GLenum, handle: u32) {
+    let changed = with_state(|state| match state.active_texture_unit {
+        Some(unit) => update(
+            state.textures.entry((unit, target)).or_insert(UNKNOWN),
+            handle,
+        ),
+        None => true,
+    });
+
+    if changed {
+        unsafe {
+            gl::BindTexture(target, handle);
+        }
+    } else {
+        debug_check_binding(texture_binding_query(target), handle, "A texture binding");
+    }
+}
+
+// Only switches the active unit if the texture isn't bound to it already
+pub fn bind_texture_to_unit(unit: u32, target: gl::types::GLenum, handle: u32) {
+    let bound = with_state(|state| state.textures.get(&(unit, target)) == Some(&handle));
+    if !bound {
+        active_texture(unit);
+        bind_texture(target, handle);
+    } else if cfg!(debug_assertions) {
+        debug_check_unit_binding(unit, target, handle);
+    }
+}
+
+// Bindings can only be queried on the active unit, so the check switches to
+// the unit and back to leave the same state as release builds
+fn debug_check_unit_binding(unit: u32, target: gl::types::GLenum, expected: u32) {
+    let Some(query) = texture_binding_query(target) else {
+        return;
+    };
+
+    let mut active_unit = 0;
+    let mut actual = 0;
+    unsafe {
+        gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut active_unit);
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        gl::GetIntegerv(query, &mut actual);
+        gl::ActiveTexture(active_unit as u32);
+    }
+    assert_eq!(
+        actual as u32, expected,
+        "The texture binding of unit {} was changed without going through the state cache",
+        unit
+    );
+}
+
+pub fn set_capability(capability: gl::types::GLenum, enabled: bool) {
+    let changed =
+        with_state(|state| state.capabilities.insert(capability, enabled) != Some(enabled));
+
+    if changed {
+        unsafe {
+            if enabled {
+                gl::Enable(capability);
+            } else {
+                gl::Disable(capability);
+            }
+        }
+    } else if cfg!(debug_assertions) {
+        let actual = unsafe { gl::IsEnabled(capability) } == gl::TRUE;
+        assert_eq!(
+            actual, enabled,
+            "Capability {:#x} was changed without going through the state cache",
+            capability
+        );
+    }
+}
+
+// Deleted objects are unbound by GL and their handles may be reused, so
+// bindings of them are forgotten
+pub fn forget_program(handle: u32) {
+    with_state(|state| {
+        if state.program == Some(handle) {
+            state.program = None;
+        }
+    });
+}
+
+pub fn forget_vertex_array(handle: u32) {
+    with_state(|state| {
+        if state.vertex_array == Some(handle) {
+            state.vertex_array = None;
+        }
+        state.element_buffers.remove(&handle);
+    });
+}
+
+pub fn forget_buffer(handle: u32) {
+    with_state(|state| {
+        state.buffers.retain(|_, bound| *bound != handle);
+        state.element_buffers.retain(|_, bound| *bound != handle);
+    });
+}
+
+pub fn forget_texture(handle: u32) {
+    with_state(|state| state.textures.retain(|_, bound| *bound != handle));
+}
+
+// Has to be called after state was changed by code that doesn't use the
+// functions above, e.g. a third-party renderer
+pub fn invalidate() {
+    with_state(|state| *state = GlState::default());
+}
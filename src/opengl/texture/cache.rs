@@ -0,0 +1,170 @@
+use std::rc::Rc;
+
+use super::texel_format::TexelFormat;
+use super::texture_2d::Texture2D;
+use crate::opengl::resource_tracker;
+
+pub struct CachedTexture {
+    pub texture: Rc<Texture2D>,
+    pub format: TexelFormat,
+}
+
+pub trait CacheEntry {
+    // Evicting an entry that's still used elsewhere wouldn't free it
+    fn is_shared(&self) -> bool;
+}
+
+impl CacheEntry for CachedTexture {
+    fn is_shared(&self) -> bool {
+        Rc::strong_count(&self.texture) > 1
+    }
+}
+
+// Least recently used textures are evicted first once the tracked texture
+// memory exceeds the budget (see resource_tracker::set_texture_budget)
+pub struct TextureCache<K, T: CacheEntry = CachedTexture> {
+    // Ordered from least to most recently used
+    entries: Vec<(K, T)>,
+}
+
+impl<K: PartialEq, T: CacheEntry> TextureCache<K, T> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn insert(&mut self, key: K, texture: T) {
+        self.remove(&key);
+        self.entries.push((key, texture));
+        self.evict_to_budget();
+    }
+
+    // The caller owns the texture while using it, and inserts it again afterwards
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let index = self
+            .entries
+            .iter()
+            .position(|(entry_key, _)| entry_key == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Shared textures are skipped. Returns the number of evicted textures.
+    pub fn evict_to_budget(&mut self) -> usize {
+        let mut evicted = 0;
+        let mut index = 0;
+        while resource_tracker::is_over_texture_budget() && index < self.entries.len() {
+            if self.entries[index].1.is_shared() {
+                index += 1;
+                continue;
+            }
+
+            self.entries.remove(index);
+            evicted += 1;
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opengl::resource_tracker::ResourceKind;
+
+    // Tracked like a texture, without needing a context
+    struct FakeTexture {
+        handle: u32,
+        users: Rc<()>,
+    }
+
+    impl FakeTexture {
+        fn new(handle: u32, bytes: usize) -> Self {
+            resource_tracker::register(ResourceKind::Texture, handle);
+            resource_tracker::set_size(ResourceKind::Texture, handle, gl::TEXTURE_2D, bytes);
+            Self {
+                handle,
+                users: Rc::new(()),
+            }
+        }
+    }
+
+    impl CacheEntry for FakeTexture {
+        fn is_shared(&self) -> bool {
+            Rc::strong_count(&self.users) > 1
+        }
+    }
+
+    impl Drop for FakeTexture {
+        fn drop(&mut self) {
+            resource_tracker::unregister(ResourceKind::Texture, self.handle);
+        }
+    }
+
+    fn cached_keys(cache: &TextureCache<&'static str, FakeTexture>) -> Vec<&'static str> {
+        cache.entries.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_used_textures_first() {
+        resource_tracker::set_texture_budget(Some(250));
+        let mut cache = TextureCache::new();
+        cache.insert("a", FakeTexture::new(1, 100));
+        cache.insert("b", FakeTexture::new(2, 100));
+
+        // Using "a" makes "b" the least recently used one
+        let texture = cache.remove(&"a").unwrap();
+        cache.insert("a", texture);
+        cache.insert("c", FakeTexture::new(3, 100));
+
+        assert_eq!(cached_keys(&cache), ["a", "c"]);
+        assert_eq!(resource_tracker::counts(ResourceKind::Texture).bytes, 200);
+    }
+
+    #[test]
+    fn evicts_until_the_budget_is_met() {
+        let mut cache = TextureCache::new();
+        for (handle, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.insert(key, FakeTexture::new(handle as u32, 100));
+        }
+
+        resource_tracker::set_texture_budget(Some(150));
+        assert_eq!(cache.evict_to_budget(), 3);
+        assert_eq!(cached_keys(&cache), ["d"]);
+        assert_eq!(cache.evict_to_budget(), 0);
+    }
+
+    #[test]
+    fn skips_shared_textures() {
+        let mut cache = TextureCache::new();
+        let shared = FakeTexture::new(1, 100);
+        let _user = Rc::clone(&shared.users);
+        cache.insert("shared", shared);
+        cache.insert("b", FakeTexture::new(2, 100));
+        cache.insert("c", FakeTexture::new(3, 100));
+
+        resource_tracker::set_texture_budget(Some(200));
+        assert_eq!(cache.evict_to_budget(), 1);
+        assert_eq!(cached_keys(&cache), ["shared", "c"]);
+    }
+
+    #[test]
+    fn inserting_a_key_again_replaces_its_texture() {
+        let mut cache = TextureCache::new();
+        cache.insert("a", FakeTexture::new(1, 100));
+        cache.insert("b", FakeTexture::new(2, 100));
+        cache.insert("a", FakeTexture::new(3, 50));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cached_keys(&cache), ["b", "a"]);
+        assert_eq!(cache.remove(&"a").map(|texture| texture.handle), Some(3));
+        assert!(cache.remove(&"a").is_none());
+    }
+}
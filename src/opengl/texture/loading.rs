@@ -1,61 +1,352 @@
 use std::path::Path;
 use std::rc::Rc;
 
-use image::RgbaImage;
-use nalgebra::Vector2;
+use image::ImageFormat;
+use nalgebra::{Vector2, Vector3};
 
+use super::texel_format::{map_texture_image, TexelFormat, TextureImage};
 use super::texture_2d::Texture2D;
+use super::texture_2d_array::Texture2DArray;
+use super::texture_3d::Texture3D;
+use super::texture_cube_map::{CubeMapFace, TextureCubeMap};
+use super::voxel::VoxelType;
 
-pub fn create_from_image(image: RgbaImage) -> (Rc<Texture2D>, Vector2<i32>) {
+fn upload_texture_image(image: TextureImage, texture: &Texture2D) {
+    let format = image.format();
+    let storage_format = format.storage_format();
+    let source_format = format.source_format();
+    let source_data_type = format.source_data_type();
+
+    texture.set_swizzle_mask(format.swizzle_mask());
+    super::set_unpack_alignment(super::alignment_for_row(image.row_size()));
+    map_texture_image!(image, image => {
+        texture.set_image_data(image, storage_format, source_format, source_data_type)
+    });
+    super::set_unpack_alignment(4);
+}
+
+fn check_texture_can_store(image: &TextureImage, texture: &Texture2D) -> Result<(), String> {
+    let size = image.size();
+    let storage_format = image.format().storage_format();
+    if texture.can_store(size.x, size.y, storage_format) {
+        return Ok(());
+    }
+
+    Err(format!(
+        "Texture {} has immutable storage that can't hold a {}x{} {} image",
+        texture.handle(),
+        size.x,
+        size.y,
+        image.format().name()
+    ))
+}
+
+pub fn create_from_image<I: Into<TextureImage>>(image: I) -> (Rc<Texture2D>, Vector2<i32>) {
+    let image = image.into();
     let mut texture = Texture2D::new();
     texture.bind();
 
-    let mut size;
+    let size;
     {
         texture.set_wrap_mode(gl::REPEAT, gl::REPEAT);
         texture.set_filter_ops(gl::NEAREST, gl::NEAREST);
 
         // Image data needs to be flipped vertically!
-        let (width, height) = image.dimensions();
-        size = Vector2::new(width as i32, height as i32);
+        size = image.size();
 
-        texture.set_image_data(image, gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE);
+        texture.allocate_storage(size.x, size.y, image.format().storage_format());
+        upload_texture_image(image, &texture);
     }
 
     crate::opengl::texture::texture_2d::unbind();
     (Rc::new(texture), size)
 }
 
+pub fn open_texture_image<P: AsRef<Path>>(path: P) -> Result<TextureImage, String> {
+    let image = image::open(&path)
+        .map_err(|_| format!("Failed to load image: {}", path.as_ref().to_string_lossy()))?;
+
+    let mut texture_image = TextureImage::from_dynamic_image(image);
+    texture_image.flip_vertical();
+    Ok(texture_image)
+}
+
+pub fn guess_image_format(bytes: &[u8], format_hint: Option<ImageFormat>) -> Option<ImageFormat> {
+    image::guess_format(bytes).ok().or(format_hint)
+}
+
+// Decoded images are not flipped yet
+pub fn decode_texture_image(
+    bytes: &[u8],
+    format_hint: Option<ImageFormat>,
+) -> Result<TextureImage, String> {
+    let format = guess_image_format(bytes, format_hint)
+        .ok_or_else(|| String::from("Failed to detect the image format"))?;
+    let image = image::load_from_memory_with_format(bytes, format)
+        .map_err(|err| format!("Failed to decode image: {}", err))?;
+
+    Ok(TextureImage::from_dynamic_image(image))
+}
+
 pub fn create_and_load_texture_from_path<P: AsRef<Path>>(
     path: P,
 ) -> Result<(Rc<Texture2D>, Vector2<i32>), String> {
-    let image = image::open(&path)
-        .map_err(|_| format!("Failed to load image: {}", path.as_ref().to_string_lossy()))?
-        .into_rgba8();
-
-    let flipped_image = image::imageops::flip_vertical(&image);
-    Ok(create_from_image(flipped_image))
+    let image = open_texture_image(path)?;
+    Ok(create_from_image(image))
 }
 
-pub fn load_from_image_into_texture(image: RgbaImage, texture: &Texture2D) -> Vector2<i32> {
-    let (width, height) = image.dimensions();
+// Reuses the texture's storage if the size and format match
+pub fn load_from_image_into_texture<I: Into<TextureImage>>(
+    image: I,
+    texture: &Texture2D,
+) -> Result<Vector2<i32>, String> {
+    let image = image.into();
+    let size = image.size();
+    check_texture_can_store(&image, texture)?;
 
     texture.bind();
-    texture.set_image_data(image, gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE);
+    upload_texture_image(image, texture);
     crate::opengl::texture::texture_2d::unbind();
 
-    let size = Vector2::new(width as i32, height as i32);
-    size
+    Ok(size)
 }
 
 pub fn load_into_texture_from_path<P: AsRef<Path>>(
     path: P,
     texture: &Texture2D,
 ) -> Result<Vector2<i32>, String> {
-    let image = image::open(&path)
-        .map_err(|_| format!("Failed to load image: {}", path.as_ref().to_string_lossy()))?
-        .into_rgba8();
+    let image = open_texture_image(path)?;
+    load_from_image_into_texture(image, texture)
+}
+
+pub struct LoadedTexture<T> {
+    pub texture: Rc<T>,
+    pub size: Vector3<i32>,
+    pub format: TexelFormat,
+}
+
+// Layers, slices and faces all have to share one size and format
+fn check_matching_images(images: &[TextureImage]) -> Result<(Vector2<i32>, TexelFormat), String> {
+    let first_image = images
+        .first()
+        .ok_or_else(|| String::from("At least one image is required"))?;
+    let size = first_image.size();
+    let format = first_image.format();
+
+    for (index, image) in images.iter().enumerate() {
+        if image.size() != size || image.format() != format {
+            return Err(format!(
+                "Image {} is {}x{} {}, expected {}x{} {}",
+                index,
+                image.size().x,
+                image.size().y,
+                image.format().name(),
+                size.x,
+                size.y,
+                format.name()
+            ));
+        }
+    }
+
+    Ok((size, format))
+}
+
+fn open_texture_images<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<TextureImage>, String> {
+    paths.iter().map(open_texture_image).collect()
+}
+
+pub fn create_2d_array_from_images(
+    layers: Vec<TextureImage>,
+) -> Result<LoadedTexture<Texture2DArray>, String> {
+    let (size, format) = check_matching_images(&layers)?;
+    let mut texture = Texture2DArray::new();
+    texture.bind();
+
+    texture.set_wrap_mode(gl::REPEAT, gl::REPEAT);
+    texture.set_filter_ops(gl::NEAREST, gl::NEAREST);
+    texture.set_swizzle_mask(format.swizzle_mask());
+    texture.allocate_storage(size.x, size.y, layers.len() as i32, format.storage_format());
+
+    super::set_unpack_alignment(super::alignment_for_row(layers[0].row_size()));
+    for (layer, image) in layers.iter().enumerate() {
+        map_texture_image!(image, image => texture.set_layer_data_from_slice(
+            layer as i32,
+            image.as_raw(),
+            size.x,
+            size.y,
+            format.source_format(),
+            format.source_data_type(),
+        ));
+    }
+    super::set_unpack_alignment(4);
+
+    super::texture_2d_array::unbind();
+    Ok(LoadedTexture {
+        texture: Rc::new(texture),
+        size: Vector3::new(size.x, size.y, layers.len() as i32),
+        format,
+    })
+}
+
+pub fn create_2d_array_from_paths<P: AsRef<Path>>(
+    paths: &[P],
+) -> Result<LoadedTexture<Texture2DArray>, String> {
+    create_2d_array_from_images(open_texture_images(paths)?)
+}
+
+pub fn create_3d_from_raw_voxels(
+    voxels: &[u8],
+    dimensions: Vector3<i32>,
+    voxel_type: VoxelType,
+) -> Result<LoadedTexture<Texture3D>, String> {
+    if dimensions.iter().any(|dimension| *dimension <= 0) {
+        return Err(format!(
+            "Invalid volume dimensions {}x{}x{}",
+            dimensions.x, dimensions.y, dimensions.z
+        ));
+    }
+
+    let expected_size = dimensions
+        .iter()
+        .map(|dimension| *dimension as usize)
+        .product::<usize>()
+        * voxel_type.size();
+    if voxels.len() != expected_size {
+        return Err(format!(
+            "A {}x{}x{} {} volume has {} bytes, got {}",
+            dimensions.x,
+            dimensions.y,
+            dimensions.z,
+            voxel_type.name(),
+            expected_size,
+            voxels.len()
+        ));
+    }
+
+    let format = voxel_type.texel_format();
+    let mut texture = Texture3D::new();
+    texture.bind();
+
+    texture.set_wrap_mode(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE);
+    texture.set_filter_ops(gl::NEAREST, gl::NEAREST);
+    texture.set_swizzle_mask(format.swizzle_mask());
+
+    let row_size = dimensions.x as usize * format.bytes_per_texel();
+    super::set_unpack_alignment(super::alignment_for_row(row_size));
+    texture.set_image_data_from_slice(
+        voxels,
+        (dimensions.x, dimensions.y, dimensions.z),
+        format.storage_format(),
+        format.source_format(),
+        format.source_data_type(),
+    );
+    super::set_unpack_alignment(4);
+
+    super::texture_3d::unbind();
+    Ok(LoadedTexture {
+        texture: Rc::new(texture),
+        size: dimensions,
+        format,
+    })
+}
+
+// The voxels aren't kept around, so the range of their sampled values (see
+// VoxelType::sampled_value_range) is returned as well
+pub fn create_3d_from_raw_voxel_path<P: AsRef<Path>>(
+    path: P,
+    dimensions: Vector3<i32>,
+    voxel_type: VoxelType,
+) -> Result<(LoadedTexture<Texture3D>, (f32, f32)), String> {
+    let voxels = std::fs::read(&path).map_err(|err| {
+        format!(
+            "Failed to read volume {}: {}",
+            path.as_ref().to_string_lossy(),
+            err
+        )
+    })?;
+    let loaded = create_3d_from_raw_voxels(&voxels, dimensions, voxel_type)?;
+    Ok((loaded, voxel_type.sampled_value_range(&voxels)))
+}
+
+// Faces are in the order of CubeMapFace::ALL (+X, -X, +Y, -Y, +Z, -Z)
+pub fn create_cube_map_from_faces(
+    faces: Vec<TextureImage>,
+) -> Result<LoadedTexture<TextureCubeMap>, String> {
+    if faces.len() != CubeMapFace::ALL.len() {
+        return Err(format!("Cube maps need 6 faces, got {}", faces.len()));
+    }
+
+    let (size, format) = check_matching_images(&faces)?;
+    if size.x != size.y {
+        return Err(format!(
+            "Cube map faces have to be square, got {}x{}",
+            size.x, size.y
+        ));
+    }
+
+    let mut texture = TextureCubeMap::new();
+    texture.bind();
+
+    texture.set_wrap_mode(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE);
+    texture.set_filter_ops(gl::LINEAR, gl::LINEAR);
+    texture.set_swizzle_mask(format.swizzle_mask());
+
+    super::set_unpack_alignment(super::alignment_for_row(faces[0].row_size()));
+    for (face, image) in CubeMapFace::ALL.iter().zip(faces.iter()) {
+        map_texture_image!(image, image => texture.set_face_data_from_slice(
+            *face,
+            image.as_raw(),
+            size.x,
+            format.storage_format(),
+            format.source_format(),
+            format.source_data_type(),
+        ));
+    }
+    super::set_unpack_alignment(4);
+
+    super::texture_cube_map::unbind();
+    Ok(LoadedTexture {
+        texture: Rc::new(texture),
+        size: Vector3::new(size.x, size.y, 6),
+        format,
+    })
+}
+
+// The cross is 4 faces wide and 3 faces high:
+//     +Y
+// -X  +Z  +X  -Z
+//     -Y
+pub fn split_horizontal_cross(image: &TextureImage) -> Result<Vec<TextureImage>, String> {
+    let (width, height) = image.dimensions();
+    let face_size = width / 4;
+    if face_size == 0 || width != face_size * 4 || height != face_size * 3 {
+        return Err(format!(
+            "A horizontal cross has to have a 4:3 aspect ratio, got {}x{}",
+            width, height
+        ));
+    }
+
+    let face_offset = |face: &CubeMapFace| match face {
+        CubeMapFace::PositiveX => (2, 1),
+        CubeMapFace::NegativeX => (0, 1),
+        CubeMapFace::PositiveY => (1, 0),
+        CubeMapFace::NegativeY => (1, 2),
+        CubeMapFace::PositiveZ => (1, 1),
+        CubeMapFace::NegativeZ => (3, 1),
+    };
+
+    let faces = CubeMapFace::ALL
+        .iter()
+        .map(|face| {
+            let (column, row) = face_offset(face);
+            image.crop(column * face_size, row * face_size, face_size, face_size)
+        })
+        .collect();
+    Ok(faces)
+}
 
-    let flipped_image = image::imageops::flip_vertical(&image);
-    Ok(load_from_image_into_texture(flipped_image, texture))
+pub fn create_cube_map_from_horizontal_cross(
+    image: &TextureImage,
+) -> Result<LoadedTexture<TextureCubeMap>, String> {
+    create_cube_map_from_faces(split_horizontal_cross(image)?)
 }
@@ -1,23 +1,92 @@
+use std::cell::Cell;
 use std::ops::Deref;
 
+use crate::opengl::resource_tracker::{self, ResourceKind};
+use crate::opengl::state_cache;
+
+pub mod cache;
 pub mod loading;
 pub mod named_texture_bindings;
+pub mod texel_format;
 pub mod texture_2d;
+pub mod texture_2d_array;
+pub mod texture_3d;
+pub mod texture_cube_map;
+pub mod voxel;
 
 pub fn unbind(target: gl::types::GLenum) {
+    state_cache::bind_texture(target, 0);
+}
+
+pub fn supports_immutable_storage() -> bool {
+    crate::opengl::context::is_version_at_least(4, 2)
+        || crate::opengl::context::has_extension("GL_ARB_texture_storage")
+}
+
+// Image rows are tightly packed, while GL expects every row to start on a
+// 4-byte boundary by default (both for uploads and downloads)
+pub fn alignment_for_row(row_size: usize) -> i32 {
+    [8, 4, 2]
+        .into_iter()
+        .find(|alignment| row_size % alignment == 0)
+        .unwrap_or(1) as i32
+}
+
+pub fn set_unpack_alignment(alignment: i32) {
     unsafe {
-        gl::BindTexture(target, 0);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
     }
 }
 
-pub fn set_active_texture_unit(unit: u32) -> Result<(), String> {
+// Drivers may pad texels (e.g. RGB8 to 4 bytes), so this is only an estimate
+fn estimated_texel_size(storage_format: gl::types::GLenum) -> usize {
+    texel_format::TexelFormat::from_storage_format(storage_format)
+        .map_or(4, |format| format.bytes_per_texel())
+}
+
+pub fn set_pack_alignment(alignment: i32) {
     unsafe {
-        if unit >= 16 {
-            return Err(format!("Out-of-range texture unit: {}", unit));
+        gl::PixelStorei(gl::PACK_ALIGNMENT, alignment);
+    }
+}
+
+thread_local! {
+    // The limit doesn't change for a context, so it's only queried once
+    static MAX_TEXTURE_UNITS: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+// Units usable by all shader stages together
+pub fn max_combined_texture_image_units() -> u32 {
+    MAX_TEXTURE_UNITS.with(|max_units| {
+        if let Some(max_units) = max_units.get() {
+            return max_units;
         }
-        gl::ActiveTexture(gl::TEXTURE0 + unit);
-        Ok(())
+
+        let mut queried = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS, &mut queried);
+        }
+        let queried = queried.max(0) as u32;
+        max_units.set(Some(queried));
+        queried
+    })
+}
+
+fn check_texture_unit(unit: u32) -> Result<(), String> {
+    let max_units = max_combined_texture_image_units();
+    if unit >= max_units {
+        return Err(format!(
+            "Out-of-range texture unit: {} (the driver supports {})",
+            unit, max_units
+        ));
     }
+    Ok(())
+}
+
+pub fn set_active_texture_unit(unit: u32) -> Result<(), String> {
+    check_texture_unit(unit)?;
+    state_cache::active_texture(unit);
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -31,15 +100,21 @@ impl Texture {
         unsafe {
             let mut handle = 0;
             gl::GenTextures(1, &mut handle);
+            resource_tracker::register(ResourceKind::Texture, handle);
 
             Self { handle, target }
         }
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::BindTexture(self.target, self.handle);
-        }
+        state_cache::bind_texture(self.target, self.handle);
+    }
+
+    // Leaves the active unit as it is if the texture is bound to the unit
+    pub fn bind_to_unit(&self, unit: u32) -> Result<(), String> {
+        check_texture_unit(unit)?;
+        state_cache::bind_texture_to_unit(unit, self.target, self.handle);
+        Ok(())
     }
 
     pub fn set_wrap_r(&mut self, wrap_r: gl::types::GLenum) {
@@ -60,6 +135,13 @@ impl Texture {
         }
     }
 
+    pub fn set_swizzle_mask(&self, swizzle_mask: [gl::types::GLenum; 4]) {
+        let swizzle_mask = swizzle_mask.map(|component| component as i32);
+        unsafe {
+            gl::TexParameteriv(self.target, gl::TEXTURE_SWIZZLE_RGBA, swizzle_mask.as_ptr());
+        }
+    }
+
     pub fn set_filter_min(&mut self, min_filter: gl::types::GLenum) {
         unsafe {
             gl::TexParameteri(self.target, gl::TEXTURE_MIN_FILTER, min_filter as i32);
@@ -81,14 +163,101 @@ impl Texture {
         source_format: gl::types::GLenum,
         source_data_type: gl::types::GLenum,
     ) {
+        self.set_image_data_for_target_from_raw_ptr(
+            self.target,
+            data_ptr,
+            (width, height),
+            storage_format,
+            source_format,
+            source_data_type,
+        );
+    }
+
+    // Cube maps are bound as one texture, but every face is its own target
+    pub unsafe fn set_image_data_for_target_from_raw_ptr(
+        &self,
+        target: gl::types::GLenum,
+        data_ptr: *const std::ffi::c_void,
+        size: (i32, i32),
+        storage_format: gl::types::GLenum,
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        resource_tracker::set_size(
+            ResourceKind::Texture,
+            self.handle,
+            target,
+            size.0 as usize * size.1 as usize * estimated_texel_size(storage_format),
+        );
+
         unsafe {
             gl::TexImage2D(
+                target,
+                0,
+                storage_format as i32,
+                size.0,
+                size.1,
+                0,
+                source_format,
+                source_data_type,
+                data_ptr,
+            );
+        }
+    }
+
+    // For 2D array textures, the depth is the number of layers
+    pub unsafe fn set_image_data_3d_from_raw_ptr(
+        &self,
+        data_ptr: *const std::ffi::c_void,
+        size: (i32, i32, i32),
+        storage_format: gl::types::GLenum,
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        resource_tracker::set_size(
+            ResourceKind::Texture,
+            self.handle,
+            self.target,
+            size.0 as usize
+                * size.1 as usize
+                * size.2 as usize
+                * estimated_texel_size(storage_format),
+        );
+
+        unsafe {
+            gl::TexImage3D(
                 self.target,
                 0,
                 storage_format as i32,
-                width,
-                height,
+                size.0,
+                size.1,
+                size.2,
+                0,
+                source_format,
+                source_data_type,
+                data_ptr,
+            );
+        }
+    }
+
+    pub unsafe fn set_sub_image_data_3d_from_raw_ptr(
+        &self,
+        data_ptr: *const std::ffi::c_void,
+        offset: (i32, i32, i32),
+        size: (i32, i32, i32),
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        unsafe {
+            gl::TexSubImage3D(
+                self.target,
                 0,
+                offset.0,
+                offset.1,
+                offset.2,
+                size.0,
+                size.1,
+                size.2,
                 source_format,
                 source_data_type,
                 data_ptr,
@@ -96,6 +265,58 @@ impl Texture {
         }
     }
 
+    pub unsafe fn set_sub_image_data_from_raw_ptr(
+        &self,
+        data_ptr: *const std::ffi::c_void,
+        offset: (i32, i32),
+        size: (i32, i32),
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        unsafe {
+            gl::TexSubImage2D(
+                self.target,
+                0,
+                offset.0,
+                offset.1,
+                size.0,
+                size.1,
+                source_format,
+                source_data_type,
+                data_ptr,
+            );
+        }
+    }
+
+    pub unsafe fn get_image_data_into_raw_ptr(
+        &self,
+        level: i32,
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+        data_ptr: *mut std::ffi::c_void,
+    ) {
+        unsafe {
+            gl::GetTexImage(self.target, level, source_format, source_data_type, data_ptr);
+        }
+    }
+
+    pub fn allocate_immutable_storage(
+        &self,
+        levels: i32,
+        storage_format: gl::types::GLenum,
+        width: i32,
+        height: i32,
+    ) {
+        // Every mip level is a quarter of the previous one
+        let level_size = width as usize * height as usize * estimated_texel_size(storage_format);
+        let total_size = (0..levels).map(|level| level_size >> (2 * level)).sum();
+        resource_tracker::set_size(ResourceKind::Texture, self.handle, self.target, total_size);
+
+        unsafe {
+            gl::TexStorage2D(self.target, levels, storage_format, width, height);
+        }
+    }
+
     pub fn set_image_data_from_slice<T>(
         &self,
         image_data: &[T],
@@ -140,13 +361,18 @@ impl Texture {
     pub fn handle(&self) -> u32 {
         self.handle
     }
+
+    pub fn target(&self) -> gl::types::GLenum {
+        self.target
+    }
 }
 
 impl Drop for Texture {
     fn drop(&mut self) {
         unsafe {
-            println!("Deleting texture {}", self.handle);
-            gl::DeleteTextures(1, &mut self.handle);
+            resource_tracker::unregister(ResourceKind::Texture, self.handle);
+            state_cache::forget_texture(self.handle);
+            gl::DeleteTextures(1, &self.handle);
         }
     }
 }
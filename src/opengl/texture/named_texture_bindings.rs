@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 pub type TextureUnit = u32;
 
+#[derive(Clone)]
 pub struct NamedTextureBindings {
     bindings: HashMap<String, TextureUnit>,
 }
@@ -23,15 +24,30 @@ impl NamedTextureBindings {
         self.bindings.insert(name, unit);
     }
 
-    pub fn has_unit(&mut self, name: &str) -> bool {
+    pub fn remove(&mut self, name: &str) -> Option<TextureUnit> {
+        self.bindings.remove(name)
+    }
+
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+
+    pub fn has_unit(&self, name: &str) -> bool {
         self.bindings.contains_key(name)
     }
 
-    pub fn get(&mut self, name: &str) -> Option<TextureUnit> {
+    pub fn get(&self, name: &str) -> Option<TextureUnit> {
         self.bindings.get(name).copied()
     }
 
-    pub fn activate(&mut self, name: &str) -> Result<(), String> {
+    // The lowest unit no name is bound to
+    pub fn first_free_unit(&self) -> TextureUnit {
+        (0..)
+            .find(|unit| !self.bindings.values().any(|bound| bound == unit))
+            .unwrap()
+    }
+
+    pub fn activate(&self, name: &str) -> Result<(), String> {
         if let Some(unit) = self.get(name) {
             return super::set_active_texture_unit(unit);
         }
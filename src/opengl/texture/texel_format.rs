@@ -0,0 +1,522 @@
+use image::{
+    DynamicImage, GrayAlphaImage, GrayImage, ImageBuffer, Luma, LumaA, Pixel, Rgb, RgbImage, Rgba,
+    Rgba32FImage, RgbaImage,
+};
+use nalgebra::Vector2;
+
+pub type Luma16Image = ImageBuffer<Luma<u16>, Vec<u16>>;
+pub type LumaA16Image = ImageBuffer<LumaA<u16>, Vec<u16>>;
+pub type Rgb16Image = ImageBuffer<Rgb<u16>, Vec<u16>>;
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TexelFormat {
+    R8,
+    Rg8,
+    Srgb8,
+    Rgba8,
+    Srgb8Alpha8,
+    R16,
+    Rg16,
+    Rgb16,
+    Rgba16,
+    R32F,
+    Rgba32F,
+}
+
+impl TexelFormat {
+    pub const ALL: [TexelFormat; 11] = [
+        TexelFormat::R8,
+        TexelFormat::Rg8,
+        TexelFormat::Srgb8,
+        TexelFormat::Rgba8,
+        TexelFormat::Srgb8Alpha8,
+        TexelFormat::R16,
+        TexelFormat::Rg16,
+        TexelFormat::Rgb16,
+        TexelFormat::Rgba16,
+        TexelFormat::R32F,
+        TexelFormat::Rgba32F,
+    ];
+
+    pub fn from_storage_format(storage_format: gl::types::GLenum) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.storage_format() == storage_format)
+    }
+
+    pub fn storage_format(&self) -> gl::types::GLenum {
+        match self {
+            TexelFormat::R8 => gl::R8,
+            TexelFormat::Rg8 => gl::RG8,
+            TexelFormat::Srgb8 => gl::SRGB8,
+            TexelFormat::Rgba8 => gl::RGBA8,
+            TexelFormat::Srgb8Alpha8 => gl::SRGB8_ALPHA8,
+            TexelFormat::R16 => gl::R16,
+            TexelFormat::Rg16 => gl::RG16,
+            TexelFormat::Rgb16 => gl::RGB16,
+            TexelFormat::Rgba16 => gl::RGBA16,
+            TexelFormat::R32F => gl::R32F,
+            TexelFormat::Rgba32F => gl::RGBA32F,
+        }
+    }
+
+    pub fn source_format(&self) -> gl::types::GLenum {
+        match self.channel_count() {
+            1 => gl::RED,
+            2 => gl::RG,
+            3 => gl::RGB,
+            _ => gl::RGBA,
+        }
+    }
+
+    pub fn source_data_type(&self) -> gl::types::GLenum {
+        match self {
+            TexelFormat::R8
+            | TexelFormat::Rg8
+            | TexelFormat::Srgb8
+            | TexelFormat::Rgba8
+            | TexelFormat::Srgb8Alpha8 => gl::UNSIGNED_BYTE,
+            TexelFormat::R16 | TexelFormat::Rg16 | TexelFormat::Rgb16 | TexelFormat::Rgba16 => {
+                gl::UNSIGNED_SHORT
+            }
+            TexelFormat::R32F | TexelFormat::Rgba32F => gl::FLOAT,
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        match self {
+            TexelFormat::R8 | TexelFormat::R16 | TexelFormat::R32F => 1,
+            TexelFormat::Rg8 | TexelFormat::Rg16 => 2,
+            TexelFormat::Srgb8 | TexelFormat::Rgb16 => 3,
+            TexelFormat::Rgba8
+            | TexelFormat::Srgb8Alpha8
+            | TexelFormat::Rgba16
+            | TexelFormat::Rgba32F => 4,
+        }
+    }
+
+    pub fn bytes_per_texel(&self) -> usize {
+        let bytes_per_channel = match self.source_data_type() {
+            gl::UNSIGNED_BYTE => 1,
+            gl::UNSIGNED_SHORT => 2,
+            _ => 4,
+        };
+        self.channel_count() * bytes_per_channel
+    }
+
+    // Four channels of the same component type, e.g. to read back texels as
+    // RGBA
+    pub fn rgba_format(&self) -> TexelFormat {
+        match self.source_data_type() {
+            gl::UNSIGNED_BYTE => TexelFormat::Rgba8,
+            gl::UNSIGNED_SHORT => TexelFormat::Rgba16,
+            _ => TexelFormat::Rgba32F,
+        }
+    }
+
+    // Grayscale images are stored as one (or two, with alpha) channels, the
+    // swizzle mask makes them sample as RGBA again
+    pub fn swizzle_mask(&self) -> [gl::types::GLenum; 4] {
+        match self.channel_count() {
+            1 => [gl::RED, gl::RED, gl::RED, gl::ONE],
+            2 => [gl::RED, gl::RED, gl::RED, gl::GREEN],
+            3 => [gl::RED, gl::GREEN, gl::BLUE, gl::ONE],
+            _ => [gl::RED, gl::GREEN, gl::BLUE, gl::ALPHA],
+        }
+    }
+
+    // Whether the stored values are sRGB-encoded. R32F only holds raw
+    // volumes, whose intensities are shown as they are like the ones of
+    // 8- and 16-bit volumes.
+    pub fn is_srgb_encoded(&self) -> bool {
+        !matches!(self, TexelFormat::Rgba8 | TexelFormat::Rgba32F)
+    }
+
+    // Whether sampling already converts the stored values to linear ones,
+    // there are no sRGB formats with less than three channels
+    pub fn is_decoded_by_sampler(&self) -> bool {
+        matches!(self, TexelFormat::Srgb8 | TexelFormat::Srgb8Alpha8)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TexelFormat::R8 => "R8",
+            TexelFormat::Rg8 => "RG8",
+            TexelFormat::Srgb8 => "SRGB8",
+            TexelFormat::Rgba8 => "RGBA8",
+            TexelFormat::Srgb8Alpha8 => "SRGB8_ALPHA8",
+            TexelFormat::R16 => "R16",
+            TexelFormat::Rg16 => "RG16",
+            TexelFormat::Rgb16 => "RGB16",
+            TexelFormat::Rgba16 => "RGBA16",
+            TexelFormat::R32F => "R32F",
+            TexelFormat::Rgba32F => "RGBA32F",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageStatistics {
+    pub min: [f32; 4],
+    pub max: [f32; 4],
+    pub mean: [f32; 4],
+}
+
+impl ImageStatistics {
+    fn from_channel_values<I>(values: I) -> Self
+    where
+        I: Iterator<Item = [f32; 4]>,
+    {
+        let mut min = [f32::INFINITY; 4];
+        let mut max = [f32::NEG_INFINITY; 4];
+        let mut sum = [0.0f64; 4];
+        let mut count = 0usize;
+
+        for texel in values {
+            for channel in 0..4 {
+                min[channel] = min[channel].min(texel[channel]);
+                max[channel] = max[channel].max(texel[channel]);
+                sum[channel] += texel[channel] as f64;
+            }
+            count += 1;
+        }
+
+        if count == 0 {
+            return Self {
+                min: [0.0; 4],
+                max: [0.0; 4],
+                mean: [0.0; 4],
+            };
+        }
+
+        let mean = sum.map(|channel_sum| (channel_sum / count as f64) as f32);
+        Self { min, max, mean }
+    }
+}
+
+// Applies the same expression to the image buffer of every variant
+macro_rules! map_texture_image {
+    ($texture_image:expr, $image:ident => $body:expr) => {
+        match $texture_image {
+            TextureImage::Luma8($image) => $body,
+            TextureImage::LumaA8($image) => $body,
+            TextureImage::Rgb8($image) => $body,
+            TextureImage::Rgba8($image) => $body,
+            TextureImage::Luma16($image) => $body,
+            TextureImage::LumaA16($image) => $body,
+            TextureImage::Rgb16($image) => $body,
+            TextureImage::Rgba16($image) => $body,
+            TextureImage::Rgba32F($image) => $body,
+        }
+    };
+}
+
+pub(crate) use map_texture_image;
+
+fn keep_channels<T: Copy>(rgba: &[T], channel_count: usize) -> Vec<T> {
+    rgba.chunks_exact(4)
+        .flat_map(|texel| texel[..channel_count].iter().copied())
+        .collect()
+}
+
+// Expands the channels of a texel the same way the swizzle mask does
+fn expand_to_rgba<T: Copy + Into<f64>>(channels: &[T], max_value: f32) -> [f32; 4] {
+    let channel = |index: usize| channels[index].into() as f32;
+    match channels.len() {
+        1 => [channel(0), channel(0), channel(0), max_value],
+        2 => [channel(0), channel(0), channel(0), channel(1)],
+        3 => [channel(0), channel(1), channel(2), max_value],
+        _ => [channel(0), channel(1), channel(2), channel(3)],
+    }
+}
+
+// Keeps the image in the depth and channel layout it was decoded in, so
+// grayscale images don't take up four times the memory. 8-bit images are
+// stored as R8/RG8/SRGB8/SRGB8_ALPHA8, 16-bit ones as R16/RG16/RGB16/RGBA16
+// and floating-point ones as RGBA32F. 8- and 16-bit images are assumed to be
+// sRGB-encoded, floating-point ones to be linear.
+#[derive(Clone)]
+pub enum TextureImage {
+    Luma8(GrayImage),
+    LumaA8(GrayAlphaImage),
+    Rgb8(RgbImage),
+    Rgba8(RgbaImage),
+    Luma16(Luma16Image),
+    LumaA16(LumaA16Image),
+    Rgb16(Rgb16Image),
+    Rgba16(Rgba16Image),
+    Rgba32F(Rgba32FImage),
+}
+
+impl TextureImage {
+    pub fn from_dynamic_image(image: DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(image) => TextureImage::Luma8(image),
+            DynamicImage::ImageLumaA8(image) => TextureImage::LumaA8(image),
+            DynamicImage::ImageRgb8(image) => TextureImage::Rgb8(image),
+            DynamicImage::ImageRgba8(image) => TextureImage::Rgba8(image),
+            DynamicImage::ImageLuma16(image) => TextureImage::Luma16(image),
+            DynamicImage::ImageLumaA16(image) => TextureImage::LumaA16(image),
+            DynamicImage::ImageRgb16(image) => TextureImage::Rgb16(image),
+            DynamicImage::ImageRgba16(image) => TextureImage::Rgba16(image),
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                TextureImage::Rgba32F(image.into_rgba32f())
+            }
+            _ => TextureImage::Rgba8(image.into_rgba8()),
+        }
+    }
+
+    // Zero-filled, None if there's no image type for the format
+    pub fn new_with_format(format: TexelFormat, width: u32, height: u32) -> Option<Self> {
+        let image = match format {
+            TexelFormat::R8 => TextureImage::Luma8(ImageBuffer::new(width, height)),
+            TexelFormat::Rg8 => TextureImage::LumaA8(ImageBuffer::new(width, height)),
+            TexelFormat::Srgb8 => TextureImage::Rgb8(ImageBuffer::new(width, height)),
+            TexelFormat::Rgba8 | TexelFormat::Srgb8Alpha8 => {
+                TextureImage::Rgba8(ImageBuffer::new(width, height))
+            }
+            TexelFormat::R16 => TextureImage::Luma16(ImageBuffer::new(width, height)),
+            TexelFormat::Rg16 => TextureImage::LumaA16(ImageBuffer::new(width, height)),
+            TexelFormat::Rgb16 => TextureImage::Rgb16(ImageBuffer::new(width, height)),
+            TexelFormat::Rgba16 => TextureImage::Rgba16(ImageBuffer::new(width, height)),
+            TexelFormat::Rgba32F => TextureImage::Rgba32F(ImageBuffer::new(width, height)),
+            TexelFormat::R32F => return None,
+        };
+        Some(image)
+    }
+
+    // Keeps the channels of an RGBA image that the format has, e.g. of texels
+    // read back as RGBA. None if the component types don't match.
+    pub fn from_rgba_image(format: TexelFormat, rgba_image: TextureImage) -> Option<Self> {
+        let (width, height) = rgba_image.dimensions();
+        let channel_count = format.channel_count();
+        let image = match rgba_image {
+            TextureImage::Rgba8(image) if format.source_data_type() == gl::UNSIGNED_BYTE => {
+                let data = keep_channels(image.as_raw(), channel_count);
+                match channel_count {
+                    1 => TextureImage::Luma8(ImageBuffer::from_raw(width, height, data)?),
+                    2 => TextureImage::LumaA8(ImageBuffer::from_raw(width, height, data)?),
+                    3 => TextureImage::Rgb8(ImageBuffer::from_raw(width, height, data)?),
+                    _ => TextureImage::Rgba8(image),
+                }
+            }
+            TextureImage::Rgba16(image) if format.source_data_type() == gl::UNSIGNED_SHORT => {
+                let data = keep_channels(image.as_raw(), channel_count);
+                match channel_count {
+                    1 => TextureImage::Luma16(ImageBuffer::from_raw(width, height, data)?),
+                    2 => TextureImage::LumaA16(ImageBuffer::from_raw(width, height, data)?),
+                    3 => TextureImage::Rgb16(ImageBuffer::from_raw(width, height, data)?),
+                    _ => TextureImage::Rgba16(image),
+                }
+            }
+            TextureImage::Rgba32F(image) if format == TexelFormat::Rgba32F => {
+                TextureImage::Rgba32F(image)
+            }
+            _ => return None,
+        };
+        Some(image)
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut std::ffi::c_void {
+        map_texture_image!(self, image => image.as_mut_ptr() as *mut std::ffi::c_void)
+    }
+
+    pub fn format(&self) -> TexelFormat {
+        match self {
+            TextureImage::Luma8(_) => TexelFormat::R8,
+            TextureImage::LumaA8(_) => TexelFormat::Rg8,
+            TextureImage::Rgb8(_) => TexelFormat::Srgb8,
+            TextureImage::Rgba8(_) => TexelFormat::Srgb8Alpha8,
+            TextureImage::Luma16(_) => TexelFormat::R16,
+            TextureImage::LumaA16(_) => TexelFormat::Rg16,
+            TextureImage::Rgb16(_) => TexelFormat::Rgb16,
+            TextureImage::Rgba16(_) => TexelFormat::Rgba16,
+            TextureImage::Rgba32F(_) => TexelFormat::Rgba32F,
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        map_texture_image!(self, image => image.dimensions())
+    }
+
+    pub fn size(&self) -> Vector2<i32> {
+        let (width, height) = self.dimensions();
+        Vector2::new(width as i32, height as i32)
+    }
+
+    // Tightly packed, without any padding at the end of the rows
+    pub fn row_size(&self) -> usize {
+        self.dimensions().0 as usize * self.format().bytes_per_texel()
+    }
+
+    pub fn flip_vertical(&mut self) {
+        map_texture_image!(self, image => image::imageops::flip_vertical_in_place(image))
+    }
+
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> TextureImage {
+        use image::imageops::crop_imm;
+
+        match self {
+            TextureImage::Luma8(image) => {
+                TextureImage::Luma8(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::LumaA8(image) => {
+                TextureImage::LumaA8(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::Rgb8(image) => {
+                TextureImage::Rgb8(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::Rgba8(image) => {
+                TextureImage::Rgba8(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::Luma16(image) => {
+                TextureImage::Luma16(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::LumaA16(image) => {
+                TextureImage::LumaA16(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::Rgb16(image) => {
+                TextureImage::Rgb16(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::Rgba16(image) => {
+                TextureImage::Rgba16(crop_imm(image, x, y, width, height).to_image())
+            }
+            TextureImage::Rgba32F(image) => {
+                TextureImage::Rgba32F(crop_imm(image, x, y, width, height).to_image())
+            }
+        }
+    }
+
+    // Expands grayscale and RGB images to RGBA (e.g. for color management)
+    pub fn expand_to_rgba(&mut self) {
+        let expanded = match self {
+            TextureImage::Luma8(image) => {
+                TextureImage::Rgba8(DynamicImage::ImageLuma8(image.clone()).into_rgba8())
+            }
+            TextureImage::LumaA8(image) => {
+                TextureImage::Rgba8(DynamicImage::ImageLumaA8(image.clone()).into_rgba8())
+            }
+            TextureImage::Rgb8(image) => {
+                TextureImage::Rgba8(DynamicImage::ImageRgb8(image.clone()).into_rgba8())
+            }
+            TextureImage::Luma16(image) => {
+                TextureImage::Rgba16(DynamicImage::ImageLuma16(image.clone()).into_rgba16())
+            }
+            TextureImage::LumaA16(image) => {
+                TextureImage::Rgba16(DynamicImage::ImageLumaA16(image.clone()).into_rgba16())
+            }
+            TextureImage::Rgb16(image) => {
+                TextureImage::Rgba16(DynamicImage::ImageRgb16(image.clone()).into_rgba16())
+            }
+            TextureImage::Rgba8(_) | TextureImage::Rgba16(_) | TextureImage::Rgba32F(_) => {
+                return;
+            }
+        };
+        *self = expanded;
+    }
+
+    // Values are reported in the image's own range (0-255, 0-65535 or the
+    // unclamped floating-point value) and expanded to RGBA
+    pub fn texel(&self, x: u32, y: u32) -> Option<[f32; 4]> {
+        let (width, height) = self.dimensions();
+        if x >= width || y >= height {
+            return None;
+        }
+
+        let max_value = self.max_value();
+        let texel = map_texture_image!(self, image => {
+            expand_to_rgba(image.get_pixel(x, y).channels(), max_value)
+        });
+        Some(texel)
+    }
+
+    fn max_value(&self) -> f32 {
+        match self.format().source_data_type() {
+            gl::UNSIGNED_BYTE => u8::MAX as f32,
+            gl::UNSIGNED_SHORT => u16::MAX as f32,
+            _ => 1.0,
+        }
+    }
+
+    pub fn statistics(&self) -> ImageStatistics {
+        let max_value = self.max_value();
+        map_texture_image!(self, image => {
+            ImageStatistics::from_channel_values(
+                image
+                    .pixels()
+                    .map(|pixel| expand_to_rgba(pixel.channels(), max_value)),
+            )
+        })
+    }
+}
+
+impl From<DynamicImage> for TextureImage {
+    fn from(image: DynamicImage) -> Self {
+        Self::from_dynamic_image(image)
+    }
+}
+
+impl From<RgbaImage> for TextureImage {
+    fn from(image: RgbaImage) -> Self {
+        TextureImage::Rgba8(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba8_image() -> TextureImage {
+        TextureImage::Rgba8(ImageBuffer::from_fn(3, 2, |x, y| {
+            Rgba([x as u8, y as u8, 10 + x as u8, 20 + y as u8])
+        }))
+    }
+
+    #[test]
+    fn rgba_images_keep_the_channels_of_the_format() {
+        let image = TextureImage::from_rgba_image(TexelFormat::R8, rgba8_image()).unwrap();
+        assert_eq!(image.format(), TexelFormat::R8);
+        assert_eq!(image.texel(2, 1), Some([2.0, 2.0, 2.0, 255.0]));
+
+        let image = TextureImage::from_rgba_image(TexelFormat::Rg8, rgba8_image()).unwrap();
+        assert_eq!(image.format(), TexelFormat::Rg8);
+        assert_eq!(image.texel(2, 1), Some([2.0, 2.0, 2.0, 1.0]));
+
+        let image = TextureImage::from_rgba_image(TexelFormat::Srgb8, rgba8_image()).unwrap();
+        assert_eq!(image.format(), TexelFormat::Srgb8);
+        assert_eq!(image.texel(2, 1), Some([2.0, 1.0, 12.0, 255.0]));
+
+        let image = TextureImage::from_rgba_image(TexelFormat::Rgba8, rgba8_image()).unwrap();
+        assert_eq!(image.texel(2, 1), Some([2.0, 1.0, 12.0, 21.0]));
+    }
+
+    #[test]
+    fn rgba16_images_convert_to_16_bit_formats() {
+        let rgba_image = TextureImage::Rgba16(ImageBuffer::from_pixel(
+            2,
+            2,
+            Rgba([1000, 2000, 3000, 4000]),
+        ));
+        let image = TextureImage::from_rgba_image(TexelFormat::Rg16, rgba_image).unwrap();
+        assert_eq!(image.format(), TexelFormat::Rg16);
+        assert_eq!(image.texel(1, 1), Some([1000.0, 1000.0, 1000.0, 2000.0]));
+    }
+
+    #[test]
+    fn rgba_images_need_the_component_type_of_the_format() {
+        assert!(TextureImage::from_rgba_image(TexelFormat::R16, rgba8_image()).is_none());
+        assert!(TextureImage::from_rgba_image(TexelFormat::Rgba32F, rgba8_image()).is_none());
+        let float_image = TextureImage::Rgba32F(ImageBuffer::new(1, 1));
+        assert!(TextureImage::from_rgba_image(TexelFormat::R32F, float_image).is_none());
+    }
+
+    #[test]
+    fn rgba_formats_match_the_component_type() {
+        for format in TexelFormat::ALL {
+            let rgba_format = format.rgba_format();
+            assert_eq!(rgba_format.channel_count(), 4);
+            assert_eq!(rgba_format.source_data_type(), format.source_data_type());
+        }
+    }
+}
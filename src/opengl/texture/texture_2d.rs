@@ -1,20 +1,43 @@
+use std::cell::Cell;
 use std::ops::Deref;
 
+use super::texel_format::{TexelFormat, TextureImage};
 use super::Texture;
+use crate::opengl::framebuffer::Framebuffer;
+use crate::opengl::pbo::Pbo;
 
 pub fn unbind() {
     super::unbind(gl::TEXTURE_2D);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureAllocation {
+    pub width: i32,
+    pub height: i32,
+    pub storage_format: gl::types::GLenum,
+    pub immutable: bool,
+}
+
+impl TextureAllocation {
+    pub fn matches(&self, width: i32, height: i32, storage_format: gl::types::GLenum) -> bool {
+        self.width == width && self.height == height && self.storage_format == storage_format
+    }
+}
+
 #[derive(Debug)]
 pub struct Texture2D {
     texture: Texture,
+
+    // Storage only has to be (re)allocated when the size or format changes,
+    // otherwise new data is uploaded with glTexSubImage2D
+    allocation: Cell<Option<TextureAllocation>>,
 }
 
 impl Texture2D {
     pub fn new() -> Self {
         Self {
             texture: Texture::new(gl::TEXTURE_2D),
+            allocation: Cell::new(None),
         }
     }
 
@@ -22,6 +45,10 @@ impl Texture2D {
         self.texture.bind();
     }
 
+    pub fn bind_to_unit(&self, unit: u32) -> Result<(), String> {
+        self.texture.bind_to_unit(unit)
+    }
+
     pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
     where
         SetupFn: FnMut(&mut Self),
@@ -42,6 +69,68 @@ impl Texture2D {
         self.texture.set_filter_mag(mag_filter);
     }
 
+    // Can change with every upload, so this doesn't require a mutable texture
+    pub fn set_swizzle_mask(&self, swizzle_mask: [gl::types::GLenum; 4]) {
+        self.texture.set_swizzle_mask(swizzle_mask);
+    }
+
+    pub fn allocation(&self) -> Option<TextureAllocation> {
+        self.allocation.get()
+    }
+
+    // Immutable storage can't be reallocated, a new texture is needed instead
+    pub fn can_store(&self, width: i32, height: i32, storage_format: gl::types::GLenum) -> bool {
+        match self.allocation.get() {
+            Some(allocation) if allocation.immutable => {
+                allocation.matches(width, height, storage_format)
+            }
+            _ => true,
+        }
+    }
+
+    // Uses immutable storage (glTexStorage2D) when the context supports it
+    pub fn allocate_storage(&self, width: i32, height: i32, storage_format: gl::types::GLenum) {
+        if let Some(allocation) = self.allocation.get() {
+            if allocation.matches(width, height, storage_format) {
+                return;
+            }
+            assert!(
+                !allocation.immutable,
+                "Immutable texture storage can't be reallocated"
+            );
+        }
+
+        let immutable = super::supports_immutable_storage();
+        if immutable {
+            self.texture
+                .allocate_immutable_storage(1, storage_format, width, height);
+        } else {
+            unsafe {
+                // The source format only has to be valid, no data is uploaded
+                let source_format = if storage_format == gl::DEPTH_COMPONENT {
+                    gl::DEPTH_COMPONENT
+                } else {
+                    gl::RGBA
+                };
+                self.texture.set_image_data_from_raw_ptr(
+                    std::ptr::null(),
+                    width,
+                    height,
+                    storage_format,
+                    source_format,
+                    gl::UNSIGNED_BYTE,
+                );
+            }
+        }
+
+        self.allocation.set(Some(TextureAllocation {
+            width,
+            height,
+            storage_format,
+            immutable,
+        }));
+    }
+
     pub unsafe fn set_image_data_from_raw_ptr(
         &self,
         data_ptr: *const std::ffi::c_void,
@@ -51,14 +140,37 @@ impl Texture2D {
         source_format: gl::types::GLenum,
         source_data_type: gl::types::GLenum,
     ) {
-        self.texture.set_image_data_from_raw_ptr(
-            data_ptr,
-            width,
-            height,
-            storage_format,
-            source_format,
-            source_data_type,
-        )
+        match self.allocation.get() {
+            Some(allocation) if allocation.matches(width, height, storage_format) => {
+                self.texture.set_sub_image_data_from_raw_ptr(
+                    data_ptr,
+                    (0, 0),
+                    (width, height),
+                    source_format,
+                    source_data_type,
+                );
+            }
+            Some(allocation) if allocation.immutable => {
+                panic!("Immutable texture storage can't be reallocated");
+            }
+            _ => {
+                self.texture.set_image_data_from_raw_ptr(
+                    data_ptr,
+                    width,
+                    height,
+                    storage_format,
+                    source_format,
+                    source_data_type,
+                );
+
+                self.allocation.set(Some(TextureAllocation {
+                    width,
+                    height,
+                    storage_format,
+                    immutable: false,
+                }));
+            }
+        }
     }
 
     pub fn set_image_data_from_slice<T>(
@@ -70,14 +182,16 @@ impl Texture2D {
         source_format: gl::types::GLenum,
         source_data_type: gl::types::GLenum,
     ) {
-        self.texture.set_image_data_from_slice(
-            image_data,
-            width,
-            height,
-            storage_format,
-            source_format,
-            source_data_type,
-        );
+        unsafe {
+            self.set_image_data_from_raw_ptr(
+                image_data.as_ptr() as *const std::ffi::c_void,
+                width,
+                height,
+                storage_format,
+                source_format,
+                source_data_type,
+            );
+        }
     }
 
     pub fn set_image_data<P, Container>(
@@ -90,15 +204,214 @@ impl Texture2D {
         P: image::Pixel,
         Container: Deref<Target = [P::Subpixel]>,
     {
-        self.texture.set_image_data(
-            image_buffer,
+        self.set_image_data_from_slice(
+            &image_buffer,
+            image_buffer.width() as i32,
+            image_buffer.height() as i32,
             storage_format,
             source_format,
             source_data_type,
         );
     }
 
+    // The data has to be written into the PBO beforehand (see PboUploader)
+    pub fn set_image_data_from_pbo(
+        &self,
+        pbo: &Pbo,
+        width: i32,
+        height: i32,
+        storage_format: gl::types::GLenum,
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        pbo.bind();
+        unsafe {
+            // With a bound pixel unpack buffer, the pointer is an offset into it
+            self.set_image_data_from_raw_ptr(
+                std::ptr::null(),
+                width,
+                height,
+                storage_format,
+                source_format,
+                source_data_type,
+            );
+        }
+        crate::opengl::pbo::unbind();
+    }
+
+    fn level_format_and_size(&self, level: i32) -> Result<(TexelFormat, u32, u32), String> {
+        let allocation = self
+            .allocation
+            .get()
+            .ok_or_else(|| format!("Texture {} has no storage", self.handle()))?;
+        let format =
+            TexelFormat::from_storage_format(allocation.storage_format).ok_or_else(|| {
+                format!(
+                    "Texture {} has unknown storage format {:#x}",
+                    self.handle(),
+                    allocation.storage_format
+                )
+            })?;
+
+        let width = (allocation.width >> level).max(1) as u32;
+        let height = (allocation.height >> level).max(1) as u32;
+        Ok((format, width, height))
+    }
+
+    // Returns the stored values (e.g. still sRGB-encoded) with the rows in
+    // texture order, i.e. bottom row first
+    pub fn read_image(&self, level: i32) -> Result<TextureImage, String> {
+        if crate::opengl::context::is_gles() {
+            return self.read_image_via_framebuffer(level);
+        }
+
+        let (format, width, height) = self.level_format_and_size(level)?;
+        let mut image = TextureImage::new_with_format(format, width, height)
+            .ok_or_else(|| format!("{} textures can't be read back", format.name()))?;
+
+        self.bind();
+        super::set_pack_alignment(super::alignment_for_row(image.row_size()));
+        unsafe {
+            self.texture.get_image_data_into_raw_ptr(
+                level,
+                format.source_format(),
+                format.source_data_type(),
+                image.as_mut_ptr(),
+            );
+        }
+        super::set_pack_alignment(4);
+        unbind();
+
+        Ok(image)
+    }
+
+    // For contexts without glGetTexImage, the format has to be color-renderable.
+    // Only reading RGBA with the format's component type is guaranteed to
+    // work, so the texels are read as RGBA and the extra channels dropped.
+    pub fn read_image_via_framebuffer(&self, level: i32) -> Result<TextureImage, String> {
+        let (format, width, height) = self.level_format_and_size(level)?;
+        let rgba_format = format.rgba_format();
+        let mut rgba_image = TextureImage::new_with_format(rgba_format, width, height)
+            .ok_or_else(|| format!("{} textures can't be read back", format.name()))?;
+
+        let mut framebuffer = Framebuffer::new();
+        framebuffer.bind();
+        framebuffer.attach_texture_2d(gl::COLOR_ATTACHMENT0, self.handle(), level);
+
+        let read_result = framebuffer.check_status().map(|_| {
+            super::set_pack_alignment(super::alignment_for_row(rgba_image.row_size()));
+            unsafe {
+                framebuffer.read_pixels_into_raw_ptr(
+                    (width as i32, height as i32),
+                    gl::RGBA,
+                    rgba_format.source_data_type(),
+                    rgba_image.as_mut_ptr(),
+                );
+            }
+            super::set_pack_alignment(4);
+        });
+        crate::opengl::framebuffer::unbind();
+        read_result?;
+
+        TextureImage::from_rgba_image(format, rgba_image)
+            .ok_or_else(|| format!("{} textures can't be read back", format.name()))
+    }
+
     pub fn handle(&self) -> u32 {
         self.texture.handle()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glfw::Context;
+    use image::{ImageBuffer, Luma, LumaA, Rgb, Rgba};
+
+    use super::*;
+    use crate::opengl::texture::loading;
+
+    // Creates a hidden window for its context, so this needs a display
+    fn with_gl_context(test: impl FnOnce()) {
+        let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
+        glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
+        glfw.window_hint(glfw::WindowHint::OpenGlProfile(
+            glfw::OpenGlProfileHint::Core,
+        ));
+        glfw.window_hint(glfw::WindowHint::Visible(false));
+        let (mut window, _events) = glfw
+            .create_window(1, 1, "imdrip tests", glfw::WindowMode::Windowed)
+            .expect("Failed to create GLFW window.");
+
+        window.make_current();
+        gl::load_with(|s| window.get_proc_address(s) as *const _);
+        test();
+    }
+
+    // The odd width makes the rows need a smaller alignment than the default
+    fn test_images() -> Vec<TextureImage> {
+        let value = |x: u32, y: u32, channel: u32| ((x * 7 + y * 13 + channel * 29) % 256) as u8;
+        vec![
+            TextureImage::Luma8(ImageBuffer::from_fn(5, 3, |x, y| Luma([value(x, y, 0)]))),
+            TextureImage::LumaA8(ImageBuffer::from_fn(5, 3, |x, y| {
+                LumaA([value(x, y, 0), value(x, y, 1)])
+            })),
+            TextureImage::Rgb8(ImageBuffer::from_fn(5, 3, |x, y| {
+                Rgb([value(x, y, 0), value(x, y, 1), value(x, y, 2)])
+            })),
+            TextureImage::Rgba8(ImageBuffer::from_fn(5, 3, |x, y| {
+                Rgba([
+                    value(x, y, 0),
+                    value(x, y, 1),
+                    value(x, y, 2),
+                    value(x, y, 3),
+                ])
+            })),
+            TextureImage::Luma16(ImageBuffer::from_fn(5, 3, |x, y| {
+                Luma([value(x, y, 0) as u16 * 257])
+            })),
+            TextureImage::Rgba16(ImageBuffer::from_fn(5, 3, |x, y| {
+                Rgba([0, 1, 2, 3].map(|channel| value(x, y, channel) as u16 * 257))
+            })),
+            TextureImage::Rgba32F(ImageBuffer::from_fn(5, 3, |x, y| {
+                Rgba([0, 1, 2, 3].map(|channel| value(x, y, channel) as f32 / 10.0 - 5.0))
+            })),
+        ]
+    }
+
+    fn assert_same_image(actual: &TextureImage, expected: &TextureImage) {
+        assert_eq!(actual.format(), expected.format());
+        assert_eq!(actual.dimensions(), expected.dimensions());
+
+        let (width, height) = expected.dimensions();
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(
+                    actual.texel(x, y),
+                    expected.texel(x, y),
+                    "{} texel ({}, {}) differs",
+                    expected.format().name(),
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    // Run with `cargo test -- --ignored` where a display is available
+    #[test]
+    #[ignore = "needs an OpenGL context"]
+    fn uploaded_images_are_read_back_unchanged() {
+        with_gl_context(|| {
+            for image in test_images() {
+                let (texture, _) = loading::create_from_image(image.clone());
+                assert_same_image(&texture.read_image(0).unwrap(), &image);
+
+                // RGB formats aren't required to be color-renderable
+                if image.format().channel_count() != 3 {
+                    let read_back = texture.read_image_via_framebuffer(0).unwrap();
+                    assert_same_image(&read_back, &image);
+                }
+            }
+        });
+    }
+}
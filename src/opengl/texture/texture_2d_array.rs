@@ -0,0 +1,93 @@
+use super::Texture;
+
+pub fn unbind() {
+    super::unbind(gl::TEXTURE_2D_ARRAY);
+}
+
+#[derive(Debug)]
+pub struct Texture2DArray {
+    texture: Texture,
+}
+
+impl Texture2DArray {
+    pub fn new() -> Self {
+        Self {
+            texture: Texture::new(gl::TEXTURE_2D_ARRAY),
+        }
+    }
+
+    pub fn bind(&self) {
+        self.texture.bind();
+    }
+
+    pub fn bind_to_unit(&self, unit: u32) -> Result<(), String> {
+        self.texture.bind_to_unit(unit)
+    }
+
+    pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
+    where
+        SetupFn: FnMut(&mut Self),
+    {
+        self.bind();
+        setup(&mut self);
+        unbind();
+        self
+    }
+
+    pub fn set_wrap_mode(&mut self, wrap_s: gl::types::GLenum, wrap_t: gl::types::GLenum) {
+        self.texture.set_wrap_s(wrap_s);
+        self.texture.set_wrap_t(wrap_t);
+    }
+
+    pub fn set_filter_ops(&mut self, min_filter: gl::types::GLenum, mag_filter: gl::types::GLenum) {
+        self.texture.set_filter_min(min_filter);
+        self.texture.set_filter_mag(mag_filter);
+    }
+
+    pub fn set_swizzle_mask(&self, swizzle_mask: [gl::types::GLenum; 4]) {
+        self.texture.set_swizzle_mask(swizzle_mask);
+    }
+
+    // The layers' contents are undefined until they're set
+    pub fn allocate_storage(
+        &self,
+        width: i32,
+        height: i32,
+        layers: i32,
+        storage_format: gl::types::GLenum,
+    ) {
+        unsafe {
+            self.texture.set_image_data_3d_from_raw_ptr(
+                std::ptr::null(),
+                (width, height, layers),
+                storage_format,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+            );
+        }
+    }
+
+    pub fn set_layer_data_from_slice<T>(
+        &self,
+        layer: i32,
+        layer_data: &[T],
+        width: i32,
+        height: i32,
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        unsafe {
+            self.texture.set_sub_image_data_3d_from_raw_ptr(
+                layer_data.as_ptr() as *const std::ffi::c_void,
+                (0, 0, layer),
+                (width, height, 1),
+                source_format,
+                source_data_type,
+            );
+        }
+    }
+
+    pub fn handle(&self) -> u32 {
+        self.texture.handle()
+    }
+}
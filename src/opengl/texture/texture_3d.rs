@@ -0,0 +1,79 @@
+use super::Texture;
+
+pub fn unbind() {
+    super::unbind(gl::TEXTURE_3D);
+}
+
+#[derive(Debug)]
+pub struct Texture3D {
+    texture: Texture,
+}
+
+impl Texture3D {
+    pub fn new() -> Self {
+        Self {
+            texture: Texture::new(gl::TEXTURE_3D),
+        }
+    }
+
+    pub fn bind(&self) {
+        self.texture.bind();
+    }
+
+    pub fn bind_to_unit(&self, unit: u32) -> Result<(), String> {
+        self.texture.bind_to_unit(unit)
+    }
+
+    pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
+    where
+        SetupFn: FnMut(&mut Self),
+    {
+        self.bind();
+        setup(&mut self);
+        unbind();
+        self
+    }
+
+    pub fn set_wrap_mode(
+        &mut self,
+        wrap_s: gl::types::GLenum,
+        wrap_t: gl::types::GLenum,
+        wrap_r: gl::types::GLenum,
+    ) {
+        self.texture.set_wrap_s(wrap_s);
+        self.texture.set_wrap_t(wrap_t);
+        self.texture.set_wrap_r(wrap_r);
+    }
+
+    pub fn set_filter_ops(&mut self, min_filter: gl::types::GLenum, mag_filter: gl::types::GLenum) {
+        self.texture.set_filter_min(min_filter);
+        self.texture.set_filter_mag(mag_filter);
+    }
+
+    pub fn set_swizzle_mask(&self, swizzle_mask: [gl::types::GLenum; 4]) {
+        self.texture.set_swizzle_mask(swizzle_mask);
+    }
+
+    pub fn set_image_data_from_slice<T>(
+        &self,
+        image_data: &[T],
+        size: (i32, i32, i32),
+        storage_format: gl::types::GLenum,
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        unsafe {
+            self.texture.set_image_data_3d_from_raw_ptr(
+                image_data.as_ptr() as *const std::ffi::c_void,
+                size,
+                storage_format,
+                source_format,
+                source_data_type,
+            );
+        }
+    }
+
+    pub fn handle(&self) -> u32 {
+        self.texture.handle()
+    }
+}
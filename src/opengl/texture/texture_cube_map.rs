@@ -0,0 +1,126 @@
+use super::Texture;
+
+pub fn unbind() {
+    super::unbind(gl::TEXTURE_CUBE_MAP);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeMapFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeMapFace {
+    // In the order GL numbers the face targets
+    pub const ALL: [CubeMapFace; 6] = [
+        CubeMapFace::PositiveX,
+        CubeMapFace::NegativeX,
+        CubeMapFace::PositiveY,
+        CubeMapFace::NegativeY,
+        CubeMapFace::PositiveZ,
+        CubeMapFace::NegativeZ,
+    ];
+
+    pub fn gl_target(&self) -> gl::types::GLenum {
+        match self {
+            CubeMapFace::PositiveX => gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            CubeMapFace::NegativeX => gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+            CubeMapFace::PositiveY => gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+            CubeMapFace::NegativeY => gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+            CubeMapFace::PositiveZ => gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+            CubeMapFace::NegativeZ => gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CubeMapFace::PositiveX => "+X",
+            CubeMapFace::NegativeX => "-X",
+            CubeMapFace::PositiveY => "+Y",
+            CubeMapFace::NegativeY => "-Y",
+            CubeMapFace::PositiveZ => "+Z",
+            CubeMapFace::NegativeZ => "-Z",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TextureCubeMap {
+    texture: Texture,
+}
+
+impl TextureCubeMap {
+    pub fn new() -> Self {
+        Self {
+            texture: Texture::new(gl::TEXTURE_CUBE_MAP),
+        }
+    }
+
+    pub fn bind(&self) {
+        self.texture.bind();
+    }
+
+    pub fn bind_to_unit(&self, unit: u32) -> Result<(), String> {
+        self.texture.bind_to_unit(unit)
+    }
+
+    pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
+    where
+        SetupFn: FnMut(&mut Self),
+    {
+        self.bind();
+        setup(&mut self);
+        unbind();
+        self
+    }
+
+    // Seams between the faces only disappear with CLAMP_TO_EDGE on all three axes
+    pub fn set_wrap_mode(
+        &mut self,
+        wrap_s: gl::types::GLenum,
+        wrap_t: gl::types::GLenum,
+        wrap_r: gl::types::GLenum,
+    ) {
+        self.texture.set_wrap_s(wrap_s);
+        self.texture.set_wrap_t(wrap_t);
+        self.texture.set_wrap_r(wrap_r);
+    }
+
+    pub fn set_filter_ops(&mut self, min_filter: gl::types::GLenum, mag_filter: gl::types::GLenum) {
+        self.texture.set_filter_min(min_filter);
+        self.texture.set_filter_mag(mag_filter);
+    }
+
+    pub fn set_swizzle_mask(&self, swizzle_mask: [gl::types::GLenum; 4]) {
+        self.texture.set_swizzle_mask(swizzle_mask);
+    }
+
+    pub fn set_face_data_from_slice<T>(
+        &self,
+        face: CubeMapFace,
+        face_data: &[T],
+        size: i32,
+        storage_format: gl::types::GLenum,
+        source_format: gl::types::GLenum,
+        source_data_type: gl::types::GLenum,
+    ) {
+        unsafe {
+            self.texture.set_image_data_for_target_from_raw_ptr(
+                face.gl_target(),
+                face_data.as_ptr() as *const std::ffi::c_void,
+                (size, size),
+                storage_format,
+                source_format,
+                source_data_type,
+            );
+        }
+    }
+
+    pub fn handle(&self) -> u32 {
+        self.texture.handle()
+    }
+}
@@ -0,0 +1,78 @@
+use super::texel_format::TexelFormat;
+
+// Raw volumes are headerless, voxels are stored little-endian with x
+// changing fastest, then y, then z
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoxelType {
+    U8,
+    U16,
+    F32,
+}
+
+impl VoxelType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "u8" | "uint8" => Some(VoxelType::U8),
+            "u16" | "uint16" => Some(VoxelType::U16),
+            "f32" | "float" | "float32" => Some(VoxelType::F32),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoxelType::U8 => "u8",
+            VoxelType::U16 => "u16",
+            VoxelType::F32 => "f32",
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            VoxelType::U8 => 1,
+            VoxelType::U16 => 2,
+            VoxelType::F32 => 4,
+        }
+    }
+
+    pub fn texel_format(&self) -> TexelFormat {
+        match self {
+            VoxelType::U8 => TexelFormat::R8,
+            VoxelType::U16 => TexelFormat::R16,
+            VoxelType::F32 => TexelFormat::R32F,
+        }
+    }
+
+    // Range of the values as the sampler returns them (normalized for
+    // integer types), used to stretch the intensities to the displayable
+    // range. Non-finite values are ignored.
+    pub fn sampled_value_range(&self, bytes: &[u8]) -> (f32, f32) {
+        let (min, max) = match self {
+            VoxelType::U8 => value_range(bytes.iter().map(|value| *value as f32 / u8::MAX as f32)),
+            VoxelType::U16 => value_range(
+                bytes
+                    .chunks_exact(2)
+                    .map(|value| u16::from_le_bytes([value[0], value[1]]) as f32 / u16::MAX as f32),
+            ),
+            VoxelType::F32 => value_range(
+                bytes
+                    .chunks_exact(4)
+                    .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]])),
+            ),
+        };
+
+        if min > max {
+            (0.0, 1.0)
+        } else {
+            (min, max)
+        }
+    }
+}
+
+fn value_range(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    values
+        .filter(|value| value.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        })
+}
@@ -0,0 +1,20 @@
+use super::buffers::{Buffer, UniformTarget};
+use super::shader::uniform_block::{BindingPoint, Std140};
+
+pub type Ubo = Buffer<UniformTarget>;
+
+impl Buffer<UniformTarget> {
+    // Blocks bound to the same binding point read from this buffer
+    pub fn bind_base(&self, binding_point: BindingPoint) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding_point, self.handle());
+        }
+        // Binding to an indexed binding point binds the generic target too
+        super::state_cache::record_buffer_binding(gl::UNIFORM_BUFFER, self.handle());
+    }
+
+    // Expects the buffer to be bound, like copy_data
+    pub fn copy_block<B: Std140>(&mut self, block: &B, mode: gl::types::GLenum) {
+        self.copy_data(std::slice::from_ref(block), mode);
+    }
+}
@@ -2,10 +2,10 @@ pub struct Vao {
     handle: u32,
 }
 
+use super::state_cache;
+
 pub fn unbind() {
-    unsafe {
-        gl::BindVertexArray(0);
-    }
+    state_cache::bind_vertex_array(0);
 }
 
 impl Vao {
@@ -18,9 +18,7 @@ impl Vao {
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.handle);
-        }
+        state_cache::bind_vertex_array(self.handle);
     }
 
     pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
@@ -36,6 +34,7 @@ impl Vao {
 
 impl Drop for Vao {
     fn drop(&mut self) {
+        state_cache::forget_vertex_array(self.handle);
         unsafe {
             gl::DeleteVertexArrays(1, &self.handle);
         }
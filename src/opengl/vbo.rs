@@ -1,42 +1,9 @@
-use std::mem::size_of;
+use super::buffers::{ArrayTarget, Buffer};
+use super::vertex_layout::{ShaderInputType, VertexLayout};
 
-use super::buffers;
-
-pub struct Vbo {
-    handle: u32,
-}
-
-impl Vbo {
-    pub fn new() -> Self {
-        Self {
-            handle: buffers::create_buffer()
-        }
-    }
-
-    pub fn bind(&self) {
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.handle);
-        }
-    }
-
-    pub fn setup<SetupFn>(mut self, mut setup: SetupFn) -> Self
-    where
-        SetupFn: FnMut(&mut Self)
-    {
-        self.bind();
-        setup(&mut self);
-        buffers::unbind(gl::ARRAY_BUFFER);
-        self
-    }
-
-    pub fn copy_data<T>(&mut self, data: &[T], mode: gl::types::GLenum) {
-        buffers::copy_data_to_bound_target(gl::ARRAY_BUFFER, data, mode);
-    }
-
-    pub fn copy_data_static<T>(&mut self, data: &[T]) {
-        buffers::copy_data_to_bound_target_static(gl::ARRAY_BUFFER, data);
-    }
+pub type Vbo = Buffer<ArrayTarget>;
 
+impl Buffer<ArrayTarget> {
     pub unsafe fn set_vertex_attrib_pointer(
         &mut self,
         location: u32,
@@ -56,22 +23,73 @@ impl Vbo {
         );
     }
 
-    pub fn set_basic_typed_vertex_attrib_pointer<T>(
+    // Integer components are passed to int/uint shader inputs unconverted
+    pub unsafe fn set_vertex_attrib_integer_pointer(
         &mut self,
         location: u32,
         size: i32,
         data_type: gl::types::GLenum,
-        normalized: bool,
+        stride: i32,
+        offset_pointer: *const std::ffi::c_void,
     ) {
+        gl::VertexAttribIPointer(location, size, data_type, stride, offset_pointer);
+    }
+
+    // Requires OpenGL 4.1 (or ARB_vertex_attrib_64bit) for double shader inputs
+    pub unsafe fn set_vertex_attrib_double_pointer(
+        &mut self,
+        location: u32,
+        size: i32,
+        stride: i32,
+        offset_pointer: *const std::ffi::c_void,
+    ) {
+        assert!(
+            gl::VertexAttribLPointer::is_loaded(),
+            "Double vertex attributes require OpenGL 4.1"
+        );
+        gl::VertexAttribLPointer(location, size, gl::DOUBLE, stride, offset_pointer);
+    }
+
+    // Expects the locations resolved by VertexLayout::attribute_locations
+    pub fn set_vertex_layout(&mut self, layout: &VertexLayout, locations: &[u32]) {
+        let stride = layout.stride() as i32;
+        for (attribute, location) in layout.attributes().iter().zip(locations) {
+            let components = attribute.components;
+            let data_type = attribute.component_type.gl_type();
+            let offset_pointer = attribute.offset as *const std::ffi::c_void;
+            unsafe {
+                match attribute.input_type {
+                    ShaderInputType::Float => self.set_vertex_attrib_pointer(
+                        *location,
+                        components,
+                        data_type,
+                        attribute.normalized,
+                        stride,
+                        offset_pointer,
+                    ),
+                    ShaderInputType::Integer => self.set_vertex_attrib_integer_pointer(
+                        *location,
+                        components,
+                        data_type,
+                        stride,
+                        offset_pointer,
+                    ),
+                    ShaderInputType::Double => self.set_vertex_attrib_double_pointer(
+                        *location,
+                        components,
+                        stride,
+                        offset_pointer,
+                    ),
+                }
+            }
+            self.set_vertex_attrib_enabled(*location, true);
+        }
+    }
+
+    // Attributes with a non-zero divisor advance once per that many instances
+    pub fn set_vertex_attrib_divisor(&mut self, location: u32, divisor: u32) {
         unsafe {
-            self.set_vertex_attrib_pointer(
-                location,
-                size,
-                data_type,
-                normalized,
-                (size as usize * size_of::<T>()) as i32,
-                std::ptr::null(),
-            )
+            gl::VertexAttribDivisor(location, divisor);
         }
     }
 
@@ -85,9 +103,3 @@ impl Vbo {
         }
     }
 }
-
-impl Drop for Vbo {
-    fn drop(&mut self) {
-        buffers::delete_buffer(self.handle);
-    }
-}
@@ -0,0 +1,281 @@
+use nalgebra::{Vector2, Vector3, Vector4};
+
+use super::shader::shader_program::ShaderProgram;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ComponentType {
+    pub fn gl_type(&self) -> gl::types::GLenum {
+        match self {
+            ComponentType::I8 => gl::BYTE,
+            ComponentType::U8 => gl::UNSIGNED_BYTE,
+            ComponentType::I16 => gl::SHORT,
+            ComponentType::U16 => gl::UNSIGNED_SHORT,
+            ComponentType::I32 => gl::INT,
+            ComponentType::U32 => gl::UNSIGNED_INT,
+            ComponentType::F32 => gl::FLOAT,
+            ComponentType::F64 => gl::DOUBLE,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            ComponentType::I8 | ComponentType::U8 => 1,
+            ComponentType::I16 | ComponentType::U16 => 2,
+            ComponentType::I32 | ComponentType::U32 | ComponentType::F32 => 4,
+            ComponentType::F64 => 8,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, ComponentType::F32 | ComponentType::F64)
+    }
+
+    // The shader input type stored components are read as by default
+    pub const fn default_input_type(&self) -> ShaderInputType {
+        match self {
+            ComponentType::F32 => ShaderInputType::Float,
+            ComponentType::F64 => ShaderInputType::Double,
+            _ => ShaderInputType::Integer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderInputType {
+    // Converted to floats (and normalized if requested), for float/vec inputs
+    Float,
+    // Passed unconverted, for int/ivec/uint/uvec inputs
+    Integer,
+    // Passed unconverted, for double/dvec inputs (requires OpenGL 4.1)
+    Double,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeLocation {
+    Index(u32),
+    // Looked up in the shader program the vertices are drawn with
+    Name(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: AttributeLocation,
+    pub component_type: ComponentType,
+    pub components: i32,
+    pub input_type: ShaderInputType,
+    // Only used for float inputs
+    pub normalized: bool,
+    // In bytes, from the start of the vertex
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    pub fn size(&self) -> usize {
+        self.components as usize * self.component_type.size()
+    }
+}
+
+// Describes how the attributes of one vertex are laid out in a buffer,
+// the stride is the distance between consecutive vertices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    stride: usize,
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(stride: usize) -> Self {
+        Self {
+            stride,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with_attribute(mut self, attribute: VertexAttribute) -> Self {
+        let supported_input = match attribute.input_type {
+            ShaderInputType::Float => true,
+            ShaderInputType::Integer => attribute.component_type.is_integer(),
+            ShaderInputType::Double => attribute.component_type == ComponentType::F64,
+        };
+        assert!(
+            supported_input,
+            "Vertex attribute {:?} can't pass {:?} components as {:?} input",
+            attribute.location,
+            attribute.component_type,
+            attribute.input_type
+        );
+        assert!(
+            attribute.offset + attribute.size() <= self.stride,
+            "Vertex attribute {:?} doesn't fit into a stride of {} bytes",
+            attribute.location,
+            self.stride
+        );
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn with_attribute_of<T: VertexAttributeType>(
+        self,
+        location: AttributeLocation,
+        offset: usize,
+    ) -> Self {
+        self.with_attribute(VertexAttribute {
+            location,
+            component_type: T::COMPONENT_TYPE,
+            components: T::COMPONENTS,
+            input_type: T::INPUT_TYPE,
+            normalized: T::NORMALIZED,
+            offset,
+        })
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn has_named_attributes(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| matches!(attribute.location, AttributeLocation::Name(_)))
+    }
+
+    // Returns the location of every attribute, in the same order
+    pub fn attribute_locations(
+        &self,
+        shader_program: Option<&ShaderProgram>,
+    ) -> Result<Vec<u32>, String> {
+        self.attributes
+            .iter()
+            .map(|attribute| match attribute.location {
+                AttributeLocation::Index(index) => Ok(index),
+                AttributeLocation::Name(name) => shader_program
+                    .ok_or_else(|| {
+                        format!("Attribute \"{}\" can't be looked up without a shader", name)
+                    })?
+                    .attribute_location(name)
+                    .filter(|location| *location >= 0)
+                    .map(|location| location as u32)
+                    .ok_or_else(|| format!("Attribute \"{}\" is not active in the shader", name)),
+            })
+            .collect()
+    }
+}
+
+// Implemented by types stored as a single vertex attribute. Integers are
+// passed to the shader unconverted, see Normalized and AsFloat otherwise.
+pub trait VertexAttributeType: Copy {
+    const COMPONENT_TYPE: ComponentType;
+    const COMPONENTS: i32;
+    const INPUT_TYPE: ShaderInputType = Self::COMPONENT_TYPE.default_input_type();
+    const NORMALIZED: bool = false;
+}
+
+// Integer components are read as floats in [0, 1] (or [-1, 1] if signed)
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalized<T>(pub T);
+
+impl<T: VertexAttributeType> VertexAttributeType for Normalized<T> {
+    const COMPONENT_TYPE: ComponentType = T::COMPONENT_TYPE;
+    const COMPONENTS: i32 = T::COMPONENTS;
+    const INPUT_TYPE: ShaderInputType = ShaderInputType::Float;
+    const NORMALIZED: bool = true;
+}
+
+// Integer components are read as floats with the same value
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AsFloat<T>(pub T);
+
+impl<T: VertexAttributeType> VertexAttributeType for AsFloat<T> {
+    const COMPONENT_TYPE: ComponentType = T::COMPONENT_TYPE;
+    const COMPONENTS: i32 = T::COMPONENTS;
+    const INPUT_TYPE: ShaderInputType = ShaderInputType::Float;
+}
+
+macro_rules! impl_vertex_attribute_type {
+    ($component:ty, $component_type:expr) => {
+        impl_vertex_attribute_type!($component, $component_type, 1);
+        impl_vertex_attribute_type!([$component; 2], $component_type, 2);
+        impl_vertex_attribute_type!([$component; 3], $component_type, 3);
+        impl_vertex_attribute_type!([$component; 4], $component_type, 4);
+        impl_vertex_attribute_type!(Vector2<$component>, $component_type, 2);
+        impl_vertex_attribute_type!(Vector3<$component>, $component_type, 3);
+        impl_vertex_attribute_type!(Vector4<$component>, $component_type, 4);
+    };
+    ($attribute:ty, $component_type:expr, $components:literal) => {
+        impl VertexAttributeType for $attribute {
+            const COMPONENT_TYPE: ComponentType = $component_type;
+            const COMPONENTS: i32 = $components;
+        }
+    };
+}
+
+impl_vertex_attribute_type!(i8, ComponentType::I8);
+impl_vertex_attribute_type!(u8, ComponentType::U8);
+impl_vertex_attribute_type!(i16, ComponentType::I16);
+impl_vertex_attribute_type!(u16, ComponentType::U16);
+impl_vertex_attribute_type!(i32, ComponentType::I32);
+impl_vertex_attribute_type!(u32, ComponentType::U32);
+impl_vertex_attribute_type!(f32, ComponentType::F32);
+impl_vertex_attribute_type!(f64, ComponentType::F64);
+
+pub trait Vertex: Copy {
+    fn layout() -> VertexLayout;
+}
+
+// Declares a struct usable as an interleaved vertex. Fields are bound to the
+// given attribute location, or to the shader attribute with the same name.
+#[macro_export]
+macro_rules! vertex_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[location = $location:literal])?
+                $field_vis:vis $field:ident : $field_type:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy)]
+        $vis struct $name {
+            $($field_vis $field: $field_type),*
+        }
+
+        impl $crate::opengl::vertex_layout::Vertex for $name {
+            fn layout() -> $crate::opengl::vertex_layout::VertexLayout {
+                let layout =
+                    $crate::opengl::vertex_layout::VertexLayout::new(std::mem::size_of::<Self>());
+                $(
+                    let layout = layout.with_attribute_of::<$field_type>(
+                        $crate::vertex_struct!(@location $field $($location)?),
+                        std::mem::offset_of!(Self, $field),
+                    );
+                )*
+                layout
+            }
+        }
+    };
+    (@location $field:ident $location:literal) => {
+        $crate::opengl::vertex_layout::AttributeLocation::Index($location)
+    };
+    (@location $field:ident) => {
+        $crate::opengl::vertex_layout::AttributeLocation::Name(stringify!($field))
+    };
+}
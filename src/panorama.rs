@@ -0,0 +1,114 @@
+use nalgebra::{Matrix4, Perspective3, Rotation3, Vector2, Vector3};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Flat,
+    Panorama,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanoramaLayout {
+    // Longitude along the width, latitude along the height (2:1)
+    Equirectangular,
+    // Cube faces unfolded into a 4:3 cross, see loading::split_horizontal_cross
+    HorizontalCross,
+}
+
+impl PanoramaLayout {
+    // #define that switches the panorama shader to sampling a cube map
+    pub const CUBE_MAP_DEFINE: &'static str = "PANORAMA_CUBE_MAP";
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PanoramaLayout::Equirectangular => "Equirectangular",
+            PanoramaLayout::HorizontalCross => "Horizontal cross",
+        }
+    }
+
+    pub fn detect(image_size: Vector2<i32>) -> Option<Self> {
+        if image_size.x <= 0 || image_size.y <= 0 {
+            return None;
+        }
+
+        if image_size.x == image_size.y * 2 {
+            Some(PanoramaLayout::Equirectangular)
+        } else if image_size.x * 3 == image_size.y * 4 {
+            Some(PanoramaLayout::HorizontalCross)
+        } else {
+            None
+        }
+    }
+
+    // Steps through auto-detection (None) and then every layout
+    pub fn next_override(layout_override: Option<Self>) -> Option<Self> {
+        match layout_override {
+            None => Some(PanoramaLayout::Equirectangular),
+            Some(PanoramaLayout::Equirectangular) => Some(PanoramaLayout::HorizontalCross),
+            Some(PanoramaLayout::HorizontalCross) => None,
+        }
+    }
+}
+
+pub struct PanoramaCamera {
+    // In degrees, yaw turns around the up axis and pitch looks up and down
+    yaw: f32,
+    pitch: f32,
+    field_of_view: f32,
+}
+
+impl PanoramaCamera {
+    pub const ZOOM_STEP: f32 = 1.1;
+
+    const DEFAULT_FIELD_OF_VIEW: f32 = 90.0;
+    const MIN_FIELD_OF_VIEW: f32 = 10.0;
+    const MAX_FIELD_OF_VIEW: f32 = 150.0;
+    const MAX_PITCH: f32 = 89.0;
+
+    pub fn new() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            field_of_view: Self::DEFAULT_FIELD_OF_VIEW,
+        }
+    }
+
+    pub fn field_of_view(&self) -> f32 {
+        self.field_of_view
+    }
+
+    // Dragging moves the panorama along with the cursor, so the rotation per
+    // pixel depends on how much of the panorama is visible
+    pub fn rotate_by_drag(&mut self, cursor_delta: Vector2<f32>, window_height: i32) {
+        let degrees_per_pixel = self.field_of_view / window_height.max(1) as f32;
+        self.yaw = (self.yaw + cursor_delta.x * degrees_per_pixel) % 360.0;
+        self.pitch = (self.pitch + cursor_delta.y * degrees_per_pixel)
+            .clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+    }
+
+    // Positive steps zoom in
+    pub fn zoom(&mut self, steps: f32) {
+        self.field_of_view = (self.field_of_view / Self::ZOOM_STEP.powf(steps))
+            .clamp(Self::MIN_FIELD_OF_VIEW, Self::MAX_FIELD_OF_VIEW);
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    // Maps clip space positions to view directions in world space
+    pub fn inverse_view_projection(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        let projection =
+            Perspective3::new(aspect_ratio, self.field_of_view.to_radians(), 0.1, 10.0);
+        let rotation = Rotation3::from_axis_angle(&Vector3::y_axis(), self.yaw.to_radians())
+            * Rotation3::from_axis_angle(&Vector3::x_axis(), self.pitch.to_radians());
+
+        rotation.to_homogeneous() * projection.inverse()
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "Yaw {:.0}°, Pitch {:.0}°, FOV {:.0}°",
+            self.yaw, self.pitch, self.field_of_view
+        )
+    }
+}
@@ -0,0 +1,14 @@
+#pragma once
+
+const vec3 darker_grid_color = vec3(0.3);
+const vec3 lighter_grid_color = vec3(0.7);
+
+vec3 checkerboard_color(vec2 tex_coord, ivec2 window_size) {
+    const float min_tile_size = 15.0;
+
+    vec2 size_fit = window_size / min_tile_size;
+    vec2 grid_size = floor(size_fit);
+    vec2 uv = fract(tex_coord * (grid_size * 0.5)) - 0.5;
+    float grid_mix = step(uv.x * uv.y, 0.0);
+    return mix(darker_grid_color, lighter_grid_color, grid_mix);
+}
@@ -0,0 +1,17 @@
+#pragma once
+
+vec3 composite_over(vec3 background, vec4 color) {
+    return mix(background, color.rgb, color.a);
+}
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, vec3(lessThanEqual(color, vec3(0.04045))));
+}
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(color, vec3(0.0031308))));
+}
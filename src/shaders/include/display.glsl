@@ -0,0 +1,46 @@
+#pragma once
+
+#include "color.glsl"
+#include "tone_mapping.glsl"
+
+// Shared by every view shader and filled once per frame (see DisplayBlock)
+layout(std140) uniform Display {
+    float exposure;
+    float gamma;
+    int tone_mapping_operator;
+    bool tone_mapping_enabled;
+
+    // Sampled values are converted to the space the output expects: linear when
+    // compositing for an sRGB output, or the stored values when viewing "raw".
+    // Without an sRGB-capable framebuffer the output is encoded by the shader.
+    bool decode_srgb_samples;
+    bool encode_linear_samples;
+    bool linear_output;
+    bool encode_output;
+
+    ivec2 window_size;
+};
+
+vec4 prepare_sampled_color(vec4 sampled_color) {
+    if (decode_srgb_samples) {
+        sampled_color.rgb = srgb_to_linear(sampled_color.rgb);
+    } else if (encode_linear_samples) {
+        sampled_color.rgb = linear_to_srgb(sampled_color.rgb);
+    }
+
+    // Apply tone mapping (or show the linear values clamped to the displayable range)
+    if (tone_mapping_enabled) {
+        sampled_color.rgb = tone_map(sampled_color.rgb, exposure, gamma, tone_mapping_operator);
+    } else {
+        sampled_color.rgb = clamp(sampled_color.rgb, 0.0, 1.0);
+    }
+    sampled_color.a = clamp(sampled_color.a, 0.0, 1.0);
+    return sampled_color;
+}
+
+vec3 encode_final_color(vec3 final_color) {
+    if (encode_output) {
+        return linear_to_srgb(final_color);
+    }
+    return final_color;
+}
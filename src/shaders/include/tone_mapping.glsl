@@ -0,0 +1,47 @@
+#pragma once
+
+// TONE_MAPPING_* operator indices are injected by imdrip
+
+const vec3 overexposed_color = vec3(1.0, 0.0, 1.0);
+const vec3 underexposed_color = vec3(0.0, 0.0, 1.0);
+
+vec3 tone_map_reinhard(vec3 color) {
+    return color / (1.0 + color);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+vec3 tone_map_aces_filmic(vec3 color) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+vec3 tone_map_false_color(vec3 color) {
+    if (any(greaterThan(color, vec3(1.0)))) {
+        return overexposed_color;
+    }
+    if (all(lessThanEqual(color, vec3(0.0)))) {
+        return underexposed_color;
+    }
+    return color;
+}
+
+vec3 tone_map(vec3 color, float exposure, float gamma, int tone_mapping_operator) {
+    vec3 exposed_color = max(color * exp2(exposure), vec3(0.0));
+
+    vec3 mapped_color;
+    if (tone_mapping_operator == TONE_MAPPING_REINHARD) {
+        mapped_color = tone_map_reinhard(exposed_color);
+    } else if (tone_mapping_operator == TONE_MAPPING_ACES_FILMIC) {
+        mapped_color = tone_map_aces_filmic(exposed_color);
+    } else if (tone_mapping_operator == TONE_MAPPING_FALSE_COLOR) {
+        mapped_color = tone_map_false_color(exposed_color);
+    } else {
+        mapped_color = exposed_color;
+    }
+
+    return pow(clamp(mapped_color, 0.0, 1.0), vec3(1.0 / gamma));
+}
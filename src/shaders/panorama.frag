@@ -0,0 +1,44 @@
+#version 330 core
+
+#include "color.glsl"
+#include "display.glsl"
+
+#define PI 3.14159265358979
+
+in vec2 vertex_tex_coord;
+
+uniform mat4 inverse_view_projection;
+
+#ifdef PANORAMA_CUBE_MAP
+uniform samplerCube image_texture;
+#else
+uniform sampler2D image_texture;
+#endif
+
+out vec4 frag_color;
+
+vec3 view_direction() {
+    vec4 clip_position = vec4(vertex_tex_coord * 2.0 - 1.0, 1.0, 1.0);
+    vec4 world_position = inverse_view_projection * clip_position;
+    return normalize(world_position.xyz / world_position.w);
+}
+
+vec4 sample_panorama(vec3 direction) {
+#ifdef PANORAMA_CUBE_MAP
+    // Cube maps are left-handed, the camera initially looks at the +Z face
+    return texture(image_texture, vec3(direction.xy, -direction.z));
+#else
+    // The image was flipped on load, so latitude increases with the y coordinate
+    float longitude = atan(direction.x, -direction.z);
+    float latitude = asin(clamp(direction.y, -1.0, 1.0));
+    vec2 equirect_coord = vec2(longitude / (2.0 * PI) + 0.5, latitude / PI + 0.5);
+    return texture(image_texture, equirect_coord);
+#endif
+}
+
+void main() {
+    vec4 sampled_color = prepare_sampled_color(sample_panorama(view_direction()));
+
+    vec3 final_color = composite_over(vec3(0.0), sampled_color);
+    frag_color = vec4(encode_final_color(final_color), 1.0);
+}
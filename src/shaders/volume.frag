@@ -0,0 +1,60 @@
+#version 330 core
+
+#include "color.glsl"
+#include "display.glsl"
+
+in vec2 vertex_tex_coord;
+#ifdef VOLUME_MONTAGE
+// Every tile of the montage shows its own slice
+flat in float vertex_slice_position;
+#endif
+
+uniform sampler3D volume_texture;
+
+uniform int slice_axis;
+#ifndef VOLUME_MONTAGE
+uniform float slice_position;
+uniform int slice_count;
+uniform bool maximum_intensity_projection;
+#endif
+
+// Sampled values in this range are stretched to [0, 1]
+uniform vec2 intensity_range;
+
+out vec4 frag_color;
+
+vec3 volume_coord(vec2 slice_coord, float depth) {
+    if (slice_axis == SLICE_AXIS_CORONAL) {
+        return vec3(slice_coord.x, depth, slice_coord.y);
+    } else if (slice_axis == SLICE_AXIS_SAGITTAL) {
+        return vec3(depth, slice_coord);
+    }
+    return vec3(slice_coord, depth);
+}
+
+float sample_intensity(float depth) {
+    return texture(volume_texture, volume_coord(vertex_tex_coord, depth)).r;
+}
+
+void main() {
+    float intensity;
+#ifdef VOLUME_MONTAGE
+    intensity = sample_intensity(vertex_slice_position);
+#else
+    if (maximum_intensity_projection) {
+        // Brightest voxel along the slice axis
+        intensity = sample_intensity(0.5 / float(slice_count));
+        for (int slice = 1; slice < slice_count; slice++) {
+            intensity = max(intensity, sample_intensity((float(slice) + 0.5) / float(slice_count)));
+        }
+    } else {
+        intensity = sample_intensity(slice_position);
+    }
+#endif
+
+    float range = max(intensity_range.y - intensity_range.x, 1e-6);
+    intensity = (intensity - intensity_range.x) / range;
+
+    vec4 sampled_color = prepare_sampled_color(vec4(vec3(intensity), 1.0));
+    frag_color = vec4(encode_final_color(sampled_color.rgb), 1.0);
+}
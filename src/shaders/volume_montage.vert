@@ -0,0 +1,18 @@
+#version 330 core
+
+layout (location = 0) in vec2 tex_coord;
+// Lower left corner of the tile in [0, 1] window coordinates
+layout (location = 1) in vec2 tile_offset;
+layout (location = 2) in float tile_slice_position;
+
+uniform vec2 tile_size;
+
+out vec2 vertex_tex_coord;
+flat out float vertex_slice_position;
+
+void main() {
+    vec2 position = tile_offset + tex_coord * tile_size;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+    vertex_tex_coord = tex_coord;
+    vertex_slice_position = tile_slice_position;
+}
@@ -0,0 +1,124 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMappingOperator {
+    Clamp,
+    Reinhard,
+    AcesFilmic,
+    FalseColor,
+}
+
+impl ToneMappingOperator {
+    pub const ALL: [ToneMappingOperator; 4] = [
+        ToneMappingOperator::Clamp,
+        ToneMappingOperator::Reinhard,
+        ToneMappingOperator::AcesFilmic,
+        ToneMappingOperator::FalseColor,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMappingOperator::Clamp => "Clamp",
+            ToneMappingOperator::Reinhard => "Reinhard",
+            ToneMappingOperator::AcesFilmic => "ACES filmic",
+            ToneMappingOperator::FalseColor => "False color",
+        }
+    }
+
+    // Name of the #define the shader uses for this operator
+    pub fn define_name(&self) -> &'static str {
+        match self {
+            ToneMappingOperator::Clamp => "TONE_MAPPING_CLAMP",
+            ToneMappingOperator::Reinhard => "TONE_MAPPING_REINHARD",
+            ToneMappingOperator::AcesFilmic => "TONE_MAPPING_ACES_FILMIC",
+            ToneMappingOperator::FalseColor => "TONE_MAPPING_FALSE_COLOR",
+        }
+    }
+
+    pub fn shader_index(&self) -> i32 {
+        Self::ALL
+            .iter()
+            .position(|operator| operator == self)
+            .unwrap() as i32
+    }
+
+    pub fn next(&self) -> Self {
+        let index = self.shader_index() as usize;
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+pub struct ToneMapping {
+    exposure: f32,
+    gamma: f32,
+    operator: ToneMappingOperator,
+    enabled: bool,
+}
+
+impl ToneMapping {
+    pub const EXPOSURE_STEP: f32 = 0.25;
+    pub const GAMMA_STEP: f32 = 0.1;
+
+    const MIN_GAMMA: f32 = 0.1;
+    const MAX_GAMMA: f32 = 5.0;
+
+    pub fn new() -> Self {
+        Self {
+            exposure: 0.0,
+            gamma: 1.0,
+            operator: ToneMappingOperator::Clamp,
+            enabled: true,
+        }
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+
+    pub fn operator(&self) -> ToneMappingOperator {
+        self.operator
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn adjust_exposure(&mut self, stops: f32) {
+        self.exposure += stops;
+    }
+
+    pub fn adjust_gamma(&mut self, delta: f32) {
+        self.gamma = (self.gamma + delta).clamp(Self::MIN_GAMMA, Self::MAX_GAMMA);
+    }
+
+    pub fn set_operator(&mut self, operator: ToneMappingOperator) {
+        self.operator = operator;
+    }
+
+    pub fn cycle_operator(&mut self) {
+        self.operator = self.operator.next();
+    }
+
+    pub fn toggle_enabled(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn status(&self) -> String {
+        if !self.enabled {
+            return String::from("Linear clamp");
+        }
+
+        format!(
+            "EV {:+.2}, Gamma {:.1}, {}",
+            self.exposure,
+            self.gamma,
+            self.operator.name()
+        )
+    }
+}
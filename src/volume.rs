@@ -0,0 +1,238 @@
+use nalgebra::{Vector2, Vector3};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceAxis {
+    // Slices through the x/y plane, stepping along z
+    Axial,
+    // Slices through the x/z plane, stepping along y
+    Coronal,
+    // Slices through the y/z plane, stepping along x
+    Sagittal,
+}
+
+impl SliceAxis {
+    pub const ALL: [SliceAxis; 3] = [SliceAxis::Axial, SliceAxis::Coronal, SliceAxis::Sagittal];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SliceAxis::Axial => "Axial",
+            SliceAxis::Coronal => "Coronal",
+            SliceAxis::Sagittal => "Sagittal",
+        }
+    }
+
+    // Name of the #define the shader uses for this axis
+    pub fn define_name(&self) -> &'static str {
+        match self {
+            SliceAxis::Axial => "SLICE_AXIS_AXIAL",
+            SliceAxis::Coronal => "SLICE_AXIS_CORONAL",
+            SliceAxis::Sagittal => "SLICE_AXIS_SAGITTAL",
+        }
+    }
+
+    pub fn shader_index(&self) -> i32 {
+        Self::ALL.iter().position(|axis| axis == self).unwrap() as i32
+    }
+
+    pub fn next(&self) -> Self {
+        let index = self.shader_index() as usize;
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    // Index of the volume dimension the slices step along
+    fn depth_dimension(&self) -> usize {
+        match self {
+            SliceAxis::Axial => 2,
+            SliceAxis::Coronal => 1,
+            SliceAxis::Sagittal => 0,
+        }
+    }
+
+    pub fn slice_count(&self, dimensions: Vector3<i32>) -> i32 {
+        dimensions[self.depth_dimension()]
+    }
+
+    pub fn slice_size(&self, dimensions: Vector3<i32>) -> Vector2<i32> {
+        match self {
+            SliceAxis::Axial => Vector2::new(dimensions.x, dimensions.y),
+            SliceAxis::Coronal => Vector2::new(dimensions.x, dimensions.z),
+            SliceAxis::Sagittal => Vector2::new(dimensions.y, dimensions.z),
+        }
+    }
+}
+
+// Parses dimensions in the form "256x256x128"
+pub fn parse_dimensions(text: &str) -> Result<Vector3<i32>, String> {
+    let dimensions = text
+        .split('x')
+        .map(|dimension| dimension.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("Invalid volume dimensions {}: {}", text, err))?;
+
+    match dimensions.as_slice() {
+        [width, height, depth] if *width > 0 && *height > 0 && *depth > 0 => {
+            Ok(Vector3::new(*width, *height, *depth))
+        }
+        _ => Err(format!(
+            "Volume dimensions have to be given as WIDTHxHEIGHTxDEPTH, got {}",
+            text
+        )),
+    }
+}
+
+pub struct VolumeView {
+    dimensions: Vector3<i32>,
+    axis: SliceAxis,
+    // One slice index per volume dimension, so switching axes keeps the position
+    slice_indices: Vector3<i32>,
+    maximum_intensity_projection: bool,
+    // Shows all slices along the axis side by side
+    montage: bool,
+}
+
+// Where one slice of a montage is drawn, in [0, 1] window coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MontageTile {
+    pub offset: Vector2<f32>,
+    pub slice_position: f32,
+}
+
+impl VolumeView {
+    // #define that switches the volume shader to drawing montage tiles
+    pub const MONTAGE_DEFINE: &'static str = "VOLUME_MONTAGE";
+
+    pub fn new(dimensions: Vector3<i32>) -> Self {
+        Self {
+            dimensions,
+            axis: SliceAxis::Axial,
+            slice_indices: dimensions / 2,
+            maximum_intensity_projection: false,
+            montage: false,
+        }
+    }
+
+    pub fn dimensions(&self) -> Vector3<i32> {
+        self.dimensions
+    }
+
+    pub fn axis(&self) -> SliceAxis {
+        self.axis
+    }
+
+    pub fn cycle_axis(&mut self) {
+        self.axis = self.axis.next();
+    }
+
+    pub fn slice_count(&self) -> i32 {
+        self.axis.slice_count(self.dimensions)
+    }
+
+    pub fn slice_index(&self) -> i32 {
+        self.slice_indices[self.axis.depth_dimension()]
+    }
+
+    pub fn move_slice(&mut self, delta: i32) {
+        let max_index = self.slice_count() - 1;
+        let index = &mut self.slice_indices[self.axis.depth_dimension()];
+        *index = (*index + delta).clamp(0, max_index);
+    }
+
+    // Texture coordinate of the center of the current slice
+    pub fn slice_position(&self) -> f32 {
+        (self.slice_index() as f32 + 0.5) / self.slice_count() as f32
+    }
+
+    pub fn slice_size(&self) -> Vector2<i32> {
+        self.axis.slice_size(self.dimensions)
+    }
+
+    pub fn maximum_intensity_projection(&self) -> bool {
+        self.maximum_intensity_projection
+    }
+
+    pub fn toggle_maximum_intensity_projection(&mut self) {
+        self.maximum_intensity_projection = !self.maximum_intensity_projection;
+    }
+
+    pub fn montage(&self) -> bool {
+        self.montage
+    }
+
+    pub fn toggle_montage(&mut self) {
+        self.montage = !self.montage;
+    }
+
+    // Columns and rows of the montage, as close to square as possible
+    pub fn montage_grid(&self) -> Vector2<i32> {
+        let slice_count = self.slice_count();
+        let columns = (slice_count as f32).sqrt().ceil() as i32;
+        let rows = (slice_count + columns - 1) / columns;
+        Vector2::new(columns, rows)
+    }
+
+    // Slices are laid out row by row starting in the top left corner
+    pub fn montage_tiles(&self) -> Vec<MontageTile> {
+        let grid = self.montage_grid();
+        let tile_size = self.montage_tile_size();
+        (0..self.slice_count())
+            .map(|slice| {
+                let column = slice % grid.x;
+                let row = slice / grid.x;
+                MontageTile {
+                    offset: Vector2::new(
+                        column as f32 * tile_size.x,
+                        1.0 - (row + 1) as f32 * tile_size.y,
+                    ),
+                    slice_position: (slice as f32 + 0.5) / self.slice_count() as f32,
+                }
+            })
+            .collect()
+    }
+
+    pub fn montage_tile_size(&self) -> Vector2<f32> {
+        let grid = self.montage_grid();
+        Vector2::new(1.0 / grid.x as f32, 1.0 / grid.y as f32)
+    }
+
+    pub fn status(&self) -> String {
+        if self.montage {
+            let grid = self.montage_grid();
+            return format!("{} montage {}x{}", self.axis.name(), grid.x, grid.y);
+        }
+        if self.maximum_intensity_projection {
+            return format!("{} MIP", self.axis.name());
+        }
+
+        format!(
+            "{} slice {}/{}",
+            self.axis.name(),
+            self.slice_index() + 1,
+            self.slice_count()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn montage_grid_fits_all_slices() {
+        let view = VolumeView::new(Vector3::new(4, 4, 10));
+        assert_eq!(view.montage_grid(), Vector2::new(4, 3));
+        assert_eq!(view.montage_tiles().len(), 10);
+    }
+
+    #[test]
+    fn montage_tiles_start_in_the_top_left_corner() {
+        let mut view = VolumeView::new(Vector3::new(2, 3, 4));
+        view.cycle_axis();
+        assert_eq!(view.slice_count(), 3);
+
+        let tiles = view.montage_tiles();
+        assert_eq!(tiles[0].offset, Vector2::new(0.0, 0.5));
+        assert_eq!(tiles[1].offset, Vector2::new(0.5, 0.5));
+        assert_eq!(tiles[2].offset, Vector2::new(0.0, 0.0));
+        assert_eq!(tiles[1].slice_position, 0.5);
+    }
+}